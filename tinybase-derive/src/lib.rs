@@ -37,14 +37,14 @@ pub fn repository(input: TokenStream) -> TokenStream {
     }
     .iter()
     .map(|check_fn| {
-        return quote! {
+        quote! {
             _table.constraint(tinybase::Constraint::check(#check_fn))?;
-        };
+        }
     })
     .collect();
 
     let vis = ast.vis.clone();
-    let wrapper_name = syn::Ident::new(&format!("{}Repository", name.to_string()), name.span());
+    let wrapper_name = syn::Ident::new(&format!("{}Repository", name), name.span());
 
     let expanded = quote! {
         #[derive(Clone)]
@@ -81,19 +81,19 @@ pub fn repository(input: TokenStream) -> TokenStream {
     expanded.into()
 }
 
+/// Generated index names, index members, index methods and index initializers.
+type ProcessedFields = (
+    Vec<Ident>,
+    Vec<proc_macro2::TokenStream>,
+    Vec<proc_macro2::TokenStream>,
+    Vec<proc_macro2::TokenStream>,
+);
+
 /// Process fields and decide what should be generated for each field.
 fn process_fields<'a>(
     struct_name: &proc_macro2::Ident,
     fields: impl Iterator<Item = &'a Field>,
-) -> Result<
-    (
-        Vec<Ident>,
-        Vec<proc_macro2::TokenStream>,
-        Vec<proc_macro2::TokenStream>,
-        Vec<proc_macro2::TokenStream>,
-    ),
-    TokenStream,
-> {
+) -> Result<ProcessedFields, TokenStream> {
     let mut index_names = vec![];
     let mut index_members = vec![];
 
@@ -51,8 +51,7 @@ pub fn validate_attributes(
         if let Some(ident) = has_attribute(attrs, attr) {
             return Err(
                 syn::Error::new(ident.0.span(), "This attribute is not allowed here")
-                    .to_compile_error()
-                    .into(),
+                    .to_compile_error(),
             );
         }
     }
@@ -61,13 +60,12 @@ pub fn validate_attributes(
         let found = has_attribute(attrs, attr.0);
         if let Some(found) = found {
             if let Some(base) = base {
-                if !has_attribute(attrs, base).is_some() {
+                if has_attribute(attrs, base).is_none() {
                     return Err(syn::Error::new(
                         found.0.span(),
                         format!("This attribute requires the #[{}] attribute", base),
                     )
-                    .to_compile_error()
-                    .into());
+                    .to_compile_error());
                 }
             }
 
@@ -78,16 +76,14 @@ pub fn validate_attributes(
                             found.0.span(),
                             "This attribute is missing a parameter",
                         )
-                        .to_compile_error()
-                        .into());
+                        .to_compile_error());
                     }
                 }
                 Meta::List(_) => {
                     if !attr.1 {
                         return Err(
                             syn::Error::new(found.0.span(), "This attribute isn't a list")
-                                .to_compile_error()
-                                .into(),
+                                .to_compile_error(),
                         );
                     }
                 }
@@ -0,0 +1,241 @@
+use std::ops::RangeBounds;
+
+use tokio::sync::mpsc;
+use tokio::task;
+use tokio_stream::wrappers::ReceiverStream;
+use tokio_stream::{Stream, StreamExt};
+
+use crate::index::IndexType;
+use crate::record::Record;
+use crate::result::DbResult;
+use crate::subscriber::{Event, Subscriber};
+use crate::table::TableType;
+use crate::{Index, QueryBuilder, Table, TinyBase};
+
+/// Number of items buffered by a stream before the producer waits for the consumer.
+const STREAM_BUFFER: usize = 64;
+
+/// Run a blocking operation on the blocking thread pool of the current tokio runtime.
+async fn blocking<R: Send + 'static>(
+    f: impl FnOnce() -> DbResult<R> + Send + 'static,
+) -> DbResult<R> {
+    match task::spawn_blocking(f).await {
+        Ok(result) => result,
+        Err(err) => std::panic::resume_unwind(err.into_panic()),
+    }
+}
+
+impl TinyBase {
+    /// Async version of `TinyBase::flush`.
+    pub async fn flush_async(&self) -> DbResult<usize> {
+        Ok(self.engine.flush_async().await?)
+    }
+}
+
+impl<T: TableType + 'static> Table<T> {
+    /// Async version of `Table::insert`.
+    pub async fn insert_async(&self, value: T) -> DbResult<u64> {
+        let table = self.clone();
+        blocking(move || table.insert(value)).await
+    }
+
+    /// Async version of `Table::insert_durable`.
+    pub async fn insert_durable_async(&self, value: T) -> DbResult<u64> {
+        let table = self.clone();
+        blocking(move || table.insert_durable(value)).await
+    }
+
+    /// Async version of `Table::select`.
+    pub async fn select_async(&self, id: u64) -> DbResult<Option<Record<T>>> {
+        let table = self.clone();
+        blocking(move || table.select(id)).await
+    }
+
+    /// Async version of `Table::scan`.
+    pub async fn scan_async(&self) -> DbResult<Vec<Record<T>>> {
+        let table = self.clone();
+        blocking(move || table.scan()).await
+    }
+
+    /// Async version of `Table::update`.
+    pub async fn update_async(
+        &self,
+        ids: Vec<u64>,
+        updater: fn(T) -> T,
+    ) -> DbResult<Vec<Record<T>>> {
+        let table = self.clone();
+        blocking(move || table.update(&ids, updater)).await
+    }
+
+    /// Async version of `Table::delete`.
+    pub async fn delete_async(&self, id: u64) -> DbResult<Option<Record<T>>> {
+        let table = self.clone();
+        blocking(move || table.delete(id)).await
+    }
+
+    /// Stream every record in the table, ordered by ID.
+    ///
+    /// Records are read lazily on the blocking thread pool as the stream is consumed.
+    ///
+    /// # Returns
+    ///
+    /// A [`Stream`] yielding each [`Record`] in the table.
+    pub fn stream(&self) -> impl Stream<Item = DbResult<Record<T>>> {
+        let (tx, rx) = mpsc::channel(STREAM_BUFFER);
+
+        let table = self.clone();
+        task::spawn_blocking(move || {
+            for record in table.iter() {
+                // The stream has been dropped.
+                if tx.blocking_send(record).is_err() {
+                    break;
+                }
+            }
+        });
+
+        ReceiverStream::new(rx)
+    }
+}
+
+impl<T: TableType + 'static, I: IndexType + 'static> Index<T, I> {
+    /// Async version of `Index::select`.
+    pub async fn select_async(&self, query: I) -> DbResult<Vec<Record<T>>> {
+        let index = self.clone();
+        blocking(move || index.select(&query)).await
+    }
+
+    /// Async version of `Index::range`.
+    pub async fn range_async<R>(&self, range: R) -> DbResult<Vec<Record<T>>>
+    where
+        R: RangeBounds<I> + Send + 'static,
+    {
+        let index = self.clone();
+        blocking(move || index.range(range)).await
+    }
+
+    /// Async version of `Index::update`.
+    pub async fn update_async(&self, query: I, updater: fn(T) -> T) -> DbResult<Vec<Record<T>>> {
+        let index = self.clone();
+        blocking(move || index.update(&query, updater)).await
+    }
+
+    /// Async version of `Index::delete`.
+    pub async fn delete_async(&self, query: I) -> DbResult<Vec<Record<T>>> {
+        let index = self.clone();
+        blocking(move || index.delete(&query)).await
+    }
+}
+
+impl<T: TableType + 'static> QueryBuilder<T> {
+    /// Async version of [`QueryBuilder::select`].
+    pub async fn select_async(self) -> DbResult<Vec<Record<T>>> {
+        blocking(move || self.select()).await
+    }
+
+    /// Async version of [`QueryBuilder::update`].
+    pub async fn update_async(self, updater: fn(T) -> T) -> DbResult<Vec<Record<T>>> {
+        blocking(move || self.update(updater)).await
+    }
+
+    /// Async version of [`QueryBuilder::delete`].
+    pub async fn delete_async(self) -> DbResult<Vec<Record<T>>> {
+        blocking(move || self.delete()).await
+    }
+}
+
+impl<T: TableType + 'static> Subscriber<T> {
+    /// Convert the subscriber into a [`Stream`] of events.
+    ///
+    /// Events are sent to the stream as they are dispatched, starting with those the subscriber
+    /// had buffered. The stream ends once the table is gone or it falls too far behind, like the
+    /// subscriber would.
+    ///
+    /// # Returns
+    ///
+    /// A [`Stream`] yielding each [`Event`] received by the subscriber.
+    pub fn into_stream(self) -> impl Stream<Item = DbResult<Event<T>>> {
+        let (rx, condition) = self.into_async();
+
+        ReceiverStream::new(rx).filter_map(move |event| match &condition {
+            Some(condition) => event
+                .matches(condition)
+                .map(|matches| matches.then_some(event))
+                .transpose(),
+            None => Some(Ok(event)),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ConditionBuilder, TinyBase};
+
+    #[tokio::test]
+    async fn async_table_and_index() {
+        let db = TinyBase::new(None, true);
+        let table: Table<String> = db.open_table("test_table").unwrap();
+        let length = table.create_index("length", |value| value.len()).unwrap();
+
+        let id = table.insert_async("value1".to_string()).await.unwrap();
+        table.insert_durable_async("aa".to_string()).await.unwrap();
+        db.flush_async().await.unwrap();
+
+        assert_eq!(
+            table.select_async(id).await.unwrap().unwrap().data,
+            "value1"
+        );
+        assert_eq!(length.select_async(6).await.unwrap().len(), 1);
+        assert_eq!(length.range_async(..3).await.unwrap().len(), 1);
+
+        let updated = QueryBuilder::new(&table)
+            .with_condition(ConditionBuilder::by(&length, 2))
+            .update_async(|_| "bbb".to_string())
+            .await
+            .unwrap();
+        assert_eq!(updated.len(), 1);
+
+        let records: Vec<Record<String>> =
+            table.stream().map(|record| record.unwrap()).collect().await;
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[1].data, "bbb");
+
+        table.delete_async(id).await.unwrap();
+        assert_eq!(table.scan_async().await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn async_subscription() {
+        let db = TinyBase::new(None, true);
+        let table: Table<String> = db.open_table("test_table").unwrap();
+        let mut events = table.subscribe().unwrap().into_stream();
+
+        let id = table.insert_async("value1".to_string()).await.unwrap();
+        table.delete_async(id).await.unwrap();
+
+        assert!(matches!(events.next().await, Some(Ok(Event::Insert(record))) if record.id == id));
+        assert!(matches!(events.next().await, Some(Ok(Event::Remove(record))) if record.id == id));
+
+        // The stream ends once the table is gone.
+        drop(table);
+        assert!(events.next().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn async_subscription_filtered() {
+        let db = TinyBase::new(None, true);
+        let table: Table<String> = db.open_table("test_table").unwrap();
+        let subscriber = table
+            .subscribe_filtered(ConditionBuilder::filter(|value: &String| value.len() == 1))
+            .unwrap();
+
+        // Events received before the conversion are part of the stream.
+        let short = table.insert("a".to_string()).unwrap();
+        let mut events = subscriber.into_stream();
+        table.insert_async("aa".to_string()).await.unwrap();
+        let other = table.insert_async("b".to_string()).await.unwrap();
+
+        assert!(matches!(events.next().await, Some(Ok(event)) if event.id() == short));
+        assert!(matches!(events.next().await, Some(Ok(event)) if event.id() == other));
+    }
+}
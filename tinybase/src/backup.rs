@@ -0,0 +1,358 @@
+use std::ffi::OsString;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use sled::{Batch, Db};
+
+use crate::encoding::{decode, encode};
+use crate::result::{DbResult, TinyBaseError};
+use crate::table::{next_id, reserve_ids, META_TREE};
+
+/// Identifies a backup file, followed by the format version.
+const MAGIC: &[u8; 8] = b"TINYBASE";
+const VERSION: u32 = 2;
+
+/// Number of entries inserted at once while restoring.
+const RESTORE_BATCH_SIZE: usize = 1000;
+
+#[derive(Serialize, Deserialize)]
+struct Header {
+    version: u32,
+    /// Every ID in the backup is below this, so the restored database never generates them again.
+    next_id: u64,
+    trees: u64,
+}
+
+/// Starts each tree, which is followed by a frame for each entry and ends with a [`None`] frame.
+#[derive(Serialize, Deserialize)]
+struct TreeHeader {
+    name: Vec<u8>,
+}
+
+/// A key and value of a tree, [`None`] once every entry of the tree has been written.
+type Entry = Option<(Vec<u8>, Vec<u8>)>;
+
+/// Wraps a reader or writer to compute the checksum of every byte passing through.
+struct Checksummed<S> {
+    inner: S,
+    hasher: crc32fast::Hasher,
+}
+
+impl<S> Checksummed<S> {
+    fn new(inner: S) -> Self {
+        Self {
+            inner,
+            hasher: crc32fast::Hasher::new(),
+        }
+    }
+
+    fn checksum(&self) -> u32 {
+        self.hasher.clone().finalize()
+    }
+}
+
+impl<W: Write> Write for Checksummed<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.hasher.update(&buf[..written]);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl<R: Read> Read for Checksummed<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.hasher.update(&buf[..read]);
+        Ok(read)
+    }
+}
+
+/// Write an item prefixed by its encoded length.
+fn write_frame<S: Serialize>(writer: &mut impl Write, item: &S) -> DbResult<()> {
+    let bytes = encode(item)?;
+    writer.write_all(&(bytes.len() as u64).to_be_bytes())?;
+    writer.write_all(&bytes)?;
+
+    Ok(())
+}
+
+/// Read an item written by [`write_frame`].
+fn read_frame<D: DeserializeOwned>(reader: &mut impl Read) -> DbResult<D> {
+    let mut len = [0; 8];
+    reader.read_exact(&mut len)?;
+
+    let mut bytes = vec![0; u64::from_be_bytes(len) as usize];
+    reader.read_exact(&mut bytes)?;
+
+    decode(&bytes)
+}
+
+fn invalid(message: &str) -> TinyBaseError {
+    TinyBaseError::Backup(message.into())
+}
+
+/// Path of the file a backup is written to before it replaces the backup file.
+fn temp_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().map(OsString::from).unwrap_or_default();
+    name.push(".tmp");
+    path.with_file_name(name)
+}
+
+/// Write every tree of the database to a backup file.
+///
+/// The backup is written to a temporary file next to it, which only replaces the backup file once
+/// it is complete and persisted, so an existing backup is never left half overwritten.
+/// No tree may be written while the backup is taken, otherwise it wouldn't be consistent.
+///
+/// # Arguments
+///
+/// * `engine` - The database engine.
+/// * `path` - The path of the backup file, which is overwritten if it exists.
+pub(crate) fn backup(engine: &Db, path: &Path) -> DbResult<()> {
+    let temp_path = temp_path(path);
+
+    let result = write_backup(engine, &temp_path).and_then(|()| {
+        fs::rename(&temp_path, path)?;
+        sync_parent(path)
+    });
+    if result.is_err() {
+        let _ = fs::remove_file(&temp_path);
+    }
+
+    result
+}
+
+/// Persist the directory entry of a file, such as after it was renamed.
+fn sync_parent(path: &Path) -> DbResult<()> {
+    // Directories can only be opened to be synced on unix.
+    #[cfg(unix)]
+    if let Some(parent) = path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+    {
+        File::open(parent)?.sync_all()?;
+    }
+
+    Ok(())
+}
+
+/// Write every tree of the database to a new file, see [`backup`].
+fn write_backup(engine: &Db, path: &Path) -> DbResult<()> {
+    let names = engine.tree_names();
+
+    let mut writer = Checksummed::new(BufWriter::new(File::create(path)?));
+    writer.write_all(MAGIC)?;
+    write_frame(
+        &mut writer,
+        &Header {
+            version: VERSION,
+            next_id: next_id(engine, &engine.open_tree(META_TREE)?)?,
+            trees: names.len() as u64,
+        },
+    )?;
+
+    for name in names {
+        let tree = engine.open_tree(&name)?;
+        write_frame(
+            &mut writer,
+            &TreeHeader {
+                name: name.to_vec(),
+            },
+        )?;
+
+        // The end of the tree is marked rather than counted upfront, so the entries are only read
+        // once.
+        for entry in tree.iter() {
+            let (key, value) = entry?;
+            write_frame(&mut writer, &Some((key.as_ref(), value.as_ref())))?;
+        }
+        write_frame(&mut writer, &Entry::None)?;
+    }
+
+    let checksum = writer.checksum();
+    let mut file = writer.inner.into_inner().map_err(|err| err.into_error())?;
+    file.write_all(&checksum.to_be_bytes())?;
+    file.sync_all()?;
+
+    Ok(())
+}
+
+/// Check that a backup file is complete and unmodified, without restoring it.
+///
+/// # Arguments
+///
+/// * `path` - The path of the backup file.
+pub(crate) fn verify(path: &Path) -> DbResult<()> {
+    let file = File::open(path)?;
+    let len = file.metadata()?.len();
+    if len < (MAGIC.len() + 4) as u64 {
+        return Err(invalid("file is truncated"));
+    }
+
+    let mut reader = Checksummed::new(BufReader::new(file).take(len - 4));
+    io::copy(&mut reader, &mut io::sink())?;
+    let checksum = reader.checksum();
+
+    let mut expected = [0; 4];
+    reader.inner.into_inner().read_exact(&mut expected)?;
+    if checksum != u32::from_be_bytes(expected) {
+        return Err(invalid("checksum mismatch"));
+    }
+
+    Ok(())
+}
+
+/// Restore a backup file into an empty database.
+///
+/// The file is verified before anything is written, and each tree is checked to hold every entry
+/// of the backup once it has been restored.
+///
+/// # Arguments
+///
+/// * `engine` - The database engine.
+/// * `path` - The path of the backup file.
+pub(crate) fn restore(engine: &Db, path: &Path) -> DbResult<()> {
+    let default_name = engine.name();
+    let empty = engine.is_empty() && engine.tree_names().iter().all(|name| *name == default_name);
+    if !empty {
+        return Err(invalid("the database being restored to is not empty"));
+    }
+
+    verify(path)?;
+
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut magic = [0; MAGIC.len()];
+    reader.read_exact(&mut magic)?;
+    if &magic != MAGIC {
+        return Err(invalid("not a backup file"));
+    }
+
+    let header: Header = read_frame(&mut reader)?;
+    if header.version != VERSION {
+        return Err(invalid("unsupported format version"));
+    }
+
+    for _ in 0..header.trees {
+        let tree_header: TreeHeader = read_frame(&mut reader)?;
+        let tree = engine.open_tree(&tree_header.name)?;
+
+        let mut batch = Batch::default();
+        let mut entries = 0;
+        while let Some((key, value)) = read_frame::<Entry>(&mut reader)? {
+            batch.insert(key, value);
+
+            entries += 1;
+            if entries % RESTORE_BATCH_SIZE == 0 {
+                tree.apply_batch(std::mem::take(&mut batch))?;
+            }
+        }
+        tree.apply_batch(batch)?;
+
+        if tree.len() != entries {
+            return Err(invalid("restored tree doesn't match the backup"));
+        }
+    }
+
+    // Backups of databases written before the next ID was stored don't hold it in a tree.
+    reserve_ids(engine, &engine.open_tree(META_TREE)?, header.next_id)?;
+    engine.flush()?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Table, TinyBase};
+
+    #[test]
+    fn backup_restore() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("backup");
+
+        let db = TinyBase::new(None, true);
+        let table: Table<String> = db.open_table("test_table").unwrap();
+        let name = table
+            .create_index("name", |value| value.to_owned())
+            .unwrap();
+        let id = table.insert("value1".to_string()).unwrap();
+        table.insert("value2".to_string()).unwrap();
+
+        db.backup_to(&path).unwrap();
+        TinyBase::verify_backup(&path).unwrap();
+        assert!(!temp_path(&path).exists());
+
+        // Writes after the backup aren't part of it.
+        table.insert("value3".to_string()).unwrap();
+        assert_eq!(name.select(&"value3".to_string()).unwrap().len(), 1);
+
+        let restored = TinyBase::new(None, true);
+        restored.restore_from(&path).unwrap();
+
+        let table: Table<String> = restored.open_table("test_table").unwrap();
+        let name = table
+            .create_index("name", |value| value.to_owned())
+            .unwrap();
+        assert_eq!(table.scan().unwrap().len(), 2);
+        assert_eq!(name.select(&"value1".to_string()).unwrap()[0].id, id);
+        assert!(name.select(&"value3".to_string()).unwrap().is_empty());
+
+        // IDs of restored records are never generated again.
+        assert!(table.insert("value4".to_string()).unwrap() > id);
+
+        // A database with data can't be restored to.
+        assert!(matches!(
+            restored.restore_from(&path),
+            Err(TinyBaseError::Backup(_))
+        ));
+
+        let mut bytes = std::fs::read(&path).unwrap();
+        let middle = bytes.len() / 2;
+        bytes[middle] ^= 1;
+        std::fs::write(&path, bytes).unwrap();
+
+        let err = TinyBase::verify_backup(&path).unwrap_err();
+        assert!(matches!(err, TinyBaseError::Backup(_)));
+        let source = std::error::Error::source(&err).unwrap();
+        assert_eq!(source.to_string(), "checksum mismatch");
+        assert!(matches!(
+            TinyBase::new(None, true).restore_from(&path),
+            Err(TinyBaseError::Backup(_))
+        ));
+    }
+
+    #[test]
+    fn restore_next_id() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("backup");
+
+        let mut writer = Checksummed::new(vec![]);
+        writer.write_all(MAGIC).unwrap();
+        write_frame(
+            &mut writer,
+            &Header {
+                version: VERSION,
+                next_id: 1 << 40,
+                trees: 0,
+            },
+        )
+        .unwrap();
+        let checksum = writer.checksum();
+        let mut bytes = writer.inner;
+        bytes.extend_from_slice(&checksum.to_be_bytes());
+        std::fs::write(&path, bytes).unwrap();
+
+        // IDs far ahead are reserved at once, rather than generating every ID below them.
+        let db = TinyBase::new(None, true);
+        db.restore_from(&path).unwrap();
+        let table: Table<String> = db.open_table("test_table").unwrap();
+        assert_eq!(table.insert("value1".to_string()).unwrap(), 1 << 40);
+    }
+}
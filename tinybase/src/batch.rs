@@ -0,0 +1,119 @@
+use sled::transaction::{
+    ConflictableTransactionResult, TransactionError, Transactional, TransactionalTree,
+};
+use sled::{Db, Tree};
+
+use crate::index::IndexChanges;
+use crate::result::{DbResult, TinyBaseError};
+use crate::table::META_TREE;
+
+/// Encoded writes to a tree, a [`None`] value removes the key.
+pub(crate) type Writes = Vec<(Vec<u8>, Option<Vec<u8>>)>;
+
+/// Encoded change log entries, each being a sequence number and the ID of the record written.
+pub(crate) type LogEntries = Vec<(Vec<u8>, Vec<u8>)>;
+
+/// Writes to a table, its change log and its indexes which are committed together.
+pub(crate) struct Batch {
+    pub(crate) root: Tree,
+    pub(crate) log: Tree,
+    pub(crate) writes: Writes,
+    pub(crate) log_entries: LogEntries,
+    /// Oldest change log entries dropped to keep the log within its capacity.
+    pub(crate) pruned: Vec<Vec<u8>>,
+    /// Meta key and value recording the last dropped change log entry.
+    pub(crate) watermark: Option<(Vec<u8>, Vec<u8>)>,
+    /// Number of entries in the change log once the batch is committed.
+    pub(crate) log_len: usize,
+    pub(crate) indexes: Vec<IndexChanges>,
+}
+
+impl Batch {
+    /// Trees written by the batch: the root tree, the change log, then both trees of each index.
+    fn trees(&self) -> Vec<Tree> {
+        let mut trees = vec![self.root.clone(), self.log.clone()];
+        for index in &self.indexes {
+            trees.push(index.data.clone());
+            trees.push(index.ids.clone());
+        }
+
+        trees
+    }
+
+    /// Apply the batch within a transaction over the trees returned by [`Batch::trees`].
+    fn apply(
+        &self,
+        trees: &[TransactionalTree],
+        meta: &TransactionalTree,
+    ) -> ConflictableTransactionResult<(), TinyBaseError> {
+        write(&trees[0], &self.writes)?;
+
+        for (key, value) in &self.log_entries {
+            trees[1].insert(key.as_slice(), value.as_slice())?;
+        }
+        for key in &self.pruned {
+            trees[1].remove(key.as_slice())?;
+        }
+        if let Some((key, value)) = &self.watermark {
+            meta.insert(key.as_slice(), value.as_slice())?;
+        }
+
+        for (index, trees) in self.indexes.iter().zip(trees[2..].chunks(2)) {
+            index.apply(&trees[0], &trees[1], meta)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Commit batches of writes to one or more tables in a single transaction, so the tables and
+/// their indexes are never observed out of sync, even after a crash.
+///
+/// # Arguments
+///
+/// * `engine` - The database engine.
+/// * `batches` - The batches to commit, at most one per table.
+pub(crate) fn commit(engine: &Db, batches: &[&Batch]) -> DbResult<()> {
+    let mut trees = vec![engine.open_tree(META_TREE)?];
+    for batch in batches {
+        trees.extend(batch.trees());
+    }
+
+    trees
+        .as_slice()
+        .transaction(|trees| {
+            let (meta, mut rest) = trees.split_first().unwrap();
+
+            for batch in batches {
+                let (own, remaining) = rest.split_at(2 + 2 * batch.indexes.len());
+                batch.apply(own, meta)?;
+                rest = remaining;
+            }
+
+            Ok(())
+        })
+        .map_err(transaction_error)
+}
+
+/// Apply writes to a tree within a transaction.
+fn write(
+    tree: &TransactionalTree,
+    writes: &Writes,
+) -> ConflictableTransactionResult<(), TinyBaseError> {
+    for (key, value) in writes {
+        match value {
+            Some(value) => tree.insert(key.as_slice(), value.as_slice())?,
+            None => tree.remove(key.as_slice())?,
+        };
+    }
+
+    Ok(())
+}
+
+/// Convert the error of a sled transaction.
+pub(crate) fn transaction_error(err: TransactionError<TinyBaseError>) -> TinyBaseError {
+    match err {
+        TransactionError::Storage(err) => TinyBaseError::Sled(err),
+        TransactionError::Abort(err) => err,
+    }
+}
@@ -0,0 +1,235 @@
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use sled::Config;
+
+use crate::catalog::OpenTables;
+use crate::durability::GroupCommit;
+use crate::result::{DbResult, TinyBaseError};
+use crate::TinyBase;
+
+/// Trade-off the storage engine makes between disk usage and write throughput.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Mode {
+    /// Compact storage more aggressively to use less disk space.
+    #[default]
+    LowSpace,
+    /// Compact storage less often to write faster.
+    HighThroughput,
+}
+
+/// Configures and opens a [`TinyBase`].
+///
+/// Without a path, the database is kept in a temporary location which is deleted on close, as if
+/// [`TinyBaseBuilder::temporary`] was set.
+pub struct TinyBaseBuilder {
+    config: Config,
+    path: Option<PathBuf>,
+    read_only: bool,
+    create_if_missing: bool,
+    group_commit: Option<Duration>,
+}
+
+impl TinyBaseBuilder {
+    pub(crate) fn new() -> Self {
+        Self {
+            config: Config::new(),
+            path: None,
+            read_only: false,
+            create_if_missing: true,
+            group_commit: None,
+        }
+    }
+
+    /// Set the path of the database directory.
+    pub fn path(mut self, path: impl AsRef<Path>) -> Self {
+        self.config = self.config.path(path.as_ref());
+        self.path = Some(path.as_ref().to_owned());
+        self
+    }
+
+    /// Delete the database once it is closed. Databases without a path are always temporary.
+    pub fn temporary(mut self, temporary: bool) -> Self {
+        self.config = self.config.temporary(temporary);
+        self
+    }
+
+    /// Set the maximum size of the page cache in bytes.
+    pub fn cache_capacity(mut self, bytes: u64) -> Self {
+        self.config = self.config.cache_capacity(bytes);
+        self
+    }
+
+    /// Set how often writes are flushed to disk in the background, [`None`] disables the
+    /// background flush so writes are only persisted when flushed explicitly.
+    pub fn flush_every_ms(mut self, every_ms: Option<u64>) -> Self {
+        self.config = self.config.flush_every_ms(every_ms);
+        self
+    }
+
+    /// Compress stored data with zstd, this requires the `compression` feature.
+    pub fn compression(mut self, compression: bool) -> Self {
+        self.config = self.config.use_compression(compression);
+        self
+    }
+
+    /// Set the trade-off between disk usage and write throughput.
+    pub fn mode(mut self, mode: Mode) -> Self {
+        self.config = self.config.mode(match mode {
+            Mode::LowSpace => sled::Mode::LowSpace,
+            Mode::HighThroughput => sled::Mode::HighThroughput,
+        });
+        self
+    }
+
+    /// Wait up to `window` for other durable writes before flushing, so they are all persisted
+    /// with a single flush. This increases the latency of each durable write in exchange for
+    /// fewer flushes under load.
+    ///
+    /// Without a window, durable writes which arrive while a flush is running still share the
+    /// next flush.
+    pub fn group_commit(mut self, window: Duration) -> Self {
+        self.group_commit = Some(window);
+        self
+    }
+
+    /// Open the database without allowing any writes.
+    ///
+    /// Every write, including building an index which isn't up to date, fails with
+    /// [`TinyBaseError::ReadOnly`].
+    ///
+    /// This only applies to writes made through TinyBase. sled still opens the database for
+    /// writing and holds its lock, so it can't be opened by another process at the same time,
+    /// and opening a table or index which doesn't exist yet creates its empty trees. Nothing is
+    /// recorded in the catalog.
+    pub fn read_only(mut self, read_only: bool) -> Self {
+        self.read_only = read_only;
+        self
+    }
+
+    /// Create the database if it doesn't exist yet, which is the default.
+    pub fn create_if_missing(mut self, create_if_missing: bool) -> Self {
+        self.create_if_missing = create_if_missing;
+        self
+    }
+
+    /// Open the database.
+    ///
+    /// # Returns
+    ///
+    /// The opened [`TinyBase`], or an error if the database is missing, locked by another
+    /// process, corrupt or the configuration is unsupported.
+    pub fn open(self) -> DbResult<TinyBase> {
+        if let Some(path) = &self.path {
+            if !self.create_if_missing && !path.exists() {
+                return Err(TinyBaseError::NotFound(path.display().to_string()));
+            }
+        }
+
+        let config = match self.path {
+            Some(_) => self.config,
+            // sled would otherwise store the database in the working directory.
+            None => self.config.temporary(true),
+        };
+
+        let engine = config.open()?;
+
+        Ok(TinyBase {
+            group_commit: Arc::new(GroupCommit::new(&engine, self.group_commit)),
+            engine,
+            read_only: self.read_only,
+            commits: Arc::new(RwLock::new(())),
+            open_tables: OpenTables::default(),
+        })
+    }
+}
+
+/// Open a database which was just closed, retrying while sled still holds its file lock, which is
+/// released in the background once the last handle is dropped.
+#[cfg(test)]
+pub(crate) fn reopen(builder: impl Fn() -> TinyBaseBuilder) -> DbResult<TinyBase> {
+    for _ in 0..100 {
+        match builder().open() {
+            Err(TinyBaseError::Sled(sled::Error::Io(_))) => {
+                std::thread::sleep(std::time::Duration::from_millis(10))
+            }
+            result => return result,
+        }
+    }
+
+    builder().open()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::table::{next_id, META_TREE};
+    use crate::Table;
+
+    #[test]
+    fn builder_open() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("db");
+
+        let missing = TinyBase::builder()
+            .path(&path)
+            .create_if_missing(false)
+            .open();
+        assert!(matches!(missing, Err(TinyBaseError::NotFound(_))));
+
+        let db = TinyBase::builder()
+            .path(&path)
+            .cache_capacity(1024 * 1024)
+            .flush_every_ms(None)
+            .mode(Mode::HighThroughput)
+            .open()
+            .unwrap();
+        let table: Table<String> = db.open_table("test_table").unwrap();
+        let id = table.insert("value1".to_string()).unwrap();
+        table
+            .create_index("name", |value| value.to_owned())
+            .unwrap();
+        drop((table, db));
+
+        let db = reopen(|| {
+            TinyBase::builder()
+                .path(&path)
+                .read_only(true)
+                .create_if_missing(false)
+        })
+        .unwrap();
+        let table: Table<String> = db.open_table("test_table").unwrap();
+
+        assert_eq!(table.select(id).unwrap().unwrap().data, "value1");
+        let meta = db.engine.open_tree(META_TREE).unwrap();
+        let next = next_id(&db.engine, &meta).unwrap();
+        assert!(matches!(
+            table.insert("value2".to_string()),
+            Err(TinyBaseError::ReadOnly)
+        ));
+        // Rejected writes don't spend an ID.
+        assert_eq!(next_id(&db.engine, &meta).unwrap(), next);
+        assert!(matches!(table.delete(id), Err(TinyBaseError::ReadOnly)));
+
+        // Opening an up to date index doesn't write, a new one would have to be built.
+        let name = table
+            .create_index("name", |value| value.to_owned())
+            .unwrap();
+        assert_eq!(name.select(&"value1".to_string()).unwrap().len(), 1);
+        assert!(matches!(
+            table.create_index("length", |value| value.len()),
+            Err(TinyBaseError::ReadOnly)
+        ));
+    }
+
+    #[test]
+    fn builder_temporary() {
+        let db = TinyBase::builder().temporary(false).open().unwrap();
+        let table: Table<String> = db.open_table("test_table").unwrap();
+        table.insert("value1".to_string()).unwrap();
+
+        // Without a path nothing is written to the working directory.
+        assert!(!Path::new("default.sled").exists());
+    }
+}
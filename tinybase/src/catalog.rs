@@ -0,0 +1,479 @@
+use std::collections::HashMap;
+use std::sync::{Mutex, MutexGuard, Weak};
+
+use serde::{Deserialize, Serialize};
+use sled::{Batch, Db, Tree};
+
+use crate::encoding::{decode, encode};
+use crate::result::{DbResult, TinyBaseError};
+use crate::table::{SharedTable, META_TREE};
+
+/// Tree holding a [`TableInfo`] for each table, keyed by name.
+pub(crate) const CATALOG_TREE: &str = "__tinybase_catalog";
+
+/// Number of entries copied at once when renaming a table.
+const COPY_BATCH_SIZE: usize = 1000;
+
+/// Description of a table recorded in the catalog.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TableInfo {
+    pub name: String,
+    /// Name of the record type the table was last opened with, for information only.
+    pub type_name: String,
+    /// Fingerprint of the record type, which the table can only be opened with at the same
+    /// version, see [`crate::TableBuilder::fingerprint`].
+    pub fingerprint: Option<String>,
+    /// Name of the [`crate::Codec`] of the records.
+    pub codec: String,
+    /// Schema version of the records.
+    pub version: u32,
+    /// Indexes created since the table was opened.
+    pub indexes: Vec<IndexInfo>,
+    /// Constraints added since the table was opened.
+    pub constraints: Vec<ConstraintInfo>,
+}
+
+/// Description of an index recorded in the catalog.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IndexInfo {
+    pub name: String,
+    /// Name of the key type.
+    pub key_type: String,
+    /// Version of the key function.
+    pub version: u32,
+}
+
+/// Description of a constraint recorded in the catalog.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ConstraintInfo {
+    /// Records must have a unique key in the index.
+    Unique { index: String },
+    /// Records must pass a check function, keyed by the position it was added at among the
+    /// checks of a handle, so adding the same checks through another handle doesn't repeat them.
+    Check { position: usize },
+}
+
+impl TableInfo {
+    /// Description of a table which has yet to be filled in.
+    pub(crate) fn new(name: &str) -> Self {
+        Self {
+            name: name.to_owned(),
+            type_name: String::new(),
+            fingerprint: None,
+            codec: String::new(),
+            version: 0,
+            indexes: vec![],
+            constraints: vec![],
+        }
+    }
+}
+
+/// State shared by the handles of the open tables, by name.
+pub(crate) type Handles = HashMap<String, Weak<SharedTable>>;
+
+/// Tables which are currently open, so they can't be dropped or renamed while in use.
+#[derive(Default)]
+pub(crate) struct OpenTables(Mutex<Handles>);
+
+impl OpenTables {
+    /// Lock the open tables, so no table is opened until the lock is released.
+    pub(crate) fn lock(&self) -> MutexGuard<'_, Handles> {
+        self.0.lock().unwrap()
+    }
+}
+
+/// Check that a table isn't open, forgetting its state once every handle was dropped.
+pub(crate) fn check_unused(handles: &mut Handles, name: &str) -> DbResult<()> {
+    if let Some(table) = handles.get(name) {
+        if table.strong_count() > 0 {
+            return Err(TinyBaseError::TableInUse(name.to_owned()));
+        }
+
+        handles.remove(name);
+    }
+
+    Ok(())
+}
+
+/// Name of the tree holding an index.
+pub(crate) fn index_tree(table: &str, index: &str) -> String {
+    format!("{}_idx_{}", table, index)
+}
+
+/// Get the description of a table.
+pub(crate) fn get(engine: &Db, name: &str) -> DbResult<Option<TableInfo>> {
+    match engine.open_tree(CATALOG_TREE)?.get(name)? {
+        Some(bytes) => Ok(Some(decode(&bytes)?)),
+        None => Ok(None),
+    }
+}
+
+/// Get the description of every table, ordered by name.
+pub(crate) fn list(engine: &Db) -> DbResult<Vec<TableInfo>> {
+    engine
+        .open_tree(CATALOG_TREE)?
+        .iter()
+        .values()
+        .map(|bytes| decode(&bytes?))
+        .collect()
+}
+
+/// Record the description of a table.
+pub(crate) fn put(engine: &Db, info: &TableInfo) -> DbResult<()> {
+    engine
+        .open_tree(CATALOG_TREE)?
+        .insert(info.name.as_bytes(), encode(info)?)?;
+
+    Ok(())
+}
+
+/// Change the description of a table, which is retried if it is changed concurrently.
+pub(crate) fn update(engine: &Db, name: &str, f: impl Fn(&mut TableInfo)) -> DbResult<()> {
+    let catalog = engine.open_tree(CATALOG_TREE)?;
+
+    loop {
+        let Some(current) = catalog.get(name)? else {
+            return Ok(());
+        };
+
+        let mut info: TableInfo = decode(&current)?;
+        f(&mut info);
+
+        let swapped = catalog.compare_and_swap(name, Some(current), Some(encode(&info)?))?;
+        if swapped.is_ok() {
+            return Ok(());
+        }
+    }
+}
+
+/// Names of the trees holding the data of a table and its indexes.
+fn trees(name: &str, indexes: &[String]) -> Vec<String> {
+    let mut trees = vec![name.to_owned(), format!("{}#log", name)];
+    for index in indexes {
+        let tree = index_tree(name, index);
+        trees.push(format!("{}#ids", tree));
+        trees.push(tree);
+    }
+
+    trees
+}
+
+/// Keys of the metadata of a table and its indexes in the meta tree.
+fn meta_keys(name: &str, indexes: &[String]) -> Vec<String> {
+    let mut keys = vec![format!("{}#pruned", name), format!("{}#legacy", name)];
+    for index in indexes {
+        keys.push(format!("{}#index", index_tree(name, index)));
+    }
+
+    keys
+}
+
+/// Check if a table exists, either recorded in the catalog or written before the catalog existed.
+fn exists(engine: &Db, name: &str) -> DbResult<bool> {
+    if get(engine, name)?.is_some() {
+        return Ok(true);
+    }
+
+    // Opening a tree creates it, so only trees which already exist are opened.
+    let names = engine.tree_names();
+    if !names.iter().any(|tree| tree == name.as_bytes()) {
+        return Ok(false);
+    }
+
+    Ok(!engine.open_tree(name)?.is_empty())
+}
+
+/// Names of the indexes of a table which have a tree.
+///
+/// They are found from the trees rather than the catalog, so indexes which are no longer
+/// registered and indexes of tables written before the catalog existed are included.
+fn index_names(engine: &Db, name: &str) -> DbResult<Vec<String>> {
+    let prefix = index_tree(name, "");
+
+    // Trees of tables named like an index of this table aren't indexes of it.
+    let others: Vec<String> = list(engine)?
+        .into_iter()
+        .map(|table| table.name)
+        .filter(|other| other.starts_with(&prefix))
+        .collect();
+    let owned_by_other = |tree: &str| {
+        others.iter().any(|other| {
+            tree == other
+                || tree.starts_with(&format!("{}#", other))
+                || tree.starts_with(&index_tree(other, ""))
+        })
+    };
+
+    let mut indexes = vec![];
+    for tree in engine.tree_names() {
+        let tree = String::from_utf8_lossy(&tree);
+        if let Some(index) = tree.strip_prefix(&prefix) {
+            if !tree.ends_with("#ids") && !owned_by_other(&tree) {
+                indexes.push(index.to_owned());
+            }
+        }
+    }
+
+    Ok(indexes)
+}
+
+/// Delete a table along with its indexes and metadata.
+///
+/// # Returns
+///
+/// Whether the table existed.
+pub(crate) fn drop_table(engine: &Db, name: &str) -> DbResult<bool> {
+    if !exists(engine, name)? {
+        return Ok(false);
+    }
+
+    let indexes = index_names(engine, name)?;
+
+    let meta = engine.open_tree(META_TREE)?;
+    for key in meta_keys(name, &indexes) {
+        meta.remove(key)?;
+    }
+
+    for tree in trees(name, &indexes) {
+        engine.drop_tree(tree)?;
+    }
+
+    // The table is only forgotten once its trees are dropped, so a crash part way through leaves
+    // it recorded to be dropped again, rather than leaving records which would be taken for a
+    // table written before the catalog existed.
+    engine.open_tree(CATALOG_TREE)?.remove(name)?;
+
+    Ok(true)
+}
+
+/// Rename a table along with its indexes and metadata.
+///
+/// sled can't rename trees, so the data is copied to the new name before the old one is dropped.
+pub(crate) fn rename_table(engine: &Db, from: &str, to: &str) -> DbResult<()> {
+    let Some(info) = get(engine, from)? else {
+        return Err(TinyBaseError::TableNotFound(from.to_owned()));
+    };
+
+    if exists(engine, to)? {
+        return Err(TinyBaseError::TableExists(to.to_owned()));
+    }
+
+    let indexes = index_names(engine, from)?;
+    let renamed = TableInfo {
+        name: to.to_owned(),
+        ..info
+    };
+
+    let renamed_trees = trees(to, &indexes);
+    for (from_tree, to_tree) in trees(from, &indexes).iter().zip(renamed_trees) {
+        copy_tree(&engine.open_tree(from_tree)?, &engine.open_tree(to_tree)?)?;
+    }
+
+    let meta = engine.open_tree(META_TREE)?;
+    let renamed_keys = meta_keys(to, &indexes);
+    for (from_key, to_key) in meta_keys(from, &indexes).iter().zip(renamed_keys) {
+        match meta.get(from_key)? {
+            Some(value) => meta.insert(to_key, value)?,
+            None => meta.remove(to_key)?,
+        };
+    }
+
+    // The table is only renamed once the copy is complete, the old copy is dropped afterwards.
+    put(engine, &renamed)?;
+    drop_table(engine, from)?;
+
+    Ok(())
+}
+
+/// Replace the content of a tree with the content of another one.
+fn copy_tree(from: &Tree, to: &Tree) -> DbResult<()> {
+    to.clear()?;
+
+    let mut batch = Batch::default();
+    for (i, entry) in from.iter().enumerate() {
+        let (key, value) = entry?;
+        batch.insert(key, value);
+
+        if (i + 1) % COPY_BATCH_SIZE == 0 {
+            to.apply_batch(std::mem::take(&mut batch))?;
+        }
+    }
+
+    to.apply_batch(batch)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codec::Json;
+    use crate::{Constraint, Table, TinyBase};
+
+    #[test]
+    fn catalog_tables() {
+        let db = TinyBase::new(None, true);
+        let table: Table<String> = db
+            .table_builder("test_table", Json)
+            .fingerprint("text")
+            .open()
+            .unwrap();
+        let name = table
+            .create_index("name", |value| value.to_owned())
+            .unwrap();
+        table.constraint(Constraint::unique(&name)).unwrap();
+        table.constraint(Constraint::unique(&name)).unwrap();
+        table.constraint(Constraint::check(|_| true)).unwrap();
+        table.constraint(Constraint::check(|_| true)).unwrap();
+        let _: Table<u64> = db.open_table("numbers").unwrap();
+
+        let tables = db.tables().unwrap();
+        assert_eq!(tables.len(), 2);
+        assert_eq!(tables[0].name, "numbers");
+        assert_eq!(
+            tables[1],
+            TableInfo {
+                name: "test_table".to_string(),
+                type_name: std::any::type_name::<String>().to_string(),
+                fingerprint: Some("text".to_string()),
+                codec: "json".to_string(),
+                version: 1,
+                indexes: vec![IndexInfo {
+                    name: "name".to_string(),
+                    key_type: std::any::type_name::<String>().to_string(),
+                    version: 0,
+                }],
+                constraints: vec![
+                    ConstraintInfo::Unique {
+                        index: "name".to_string()
+                    },
+                    ConstraintInfo::Check { position: 0 },
+                    ConstraintInfo::Check { position: 1 },
+                ],
+            }
+        );
+
+        // Another handle keeps what the open one registered, and adding the same checks again
+        // doesn't repeat them.
+        let other: Table<String> = db.open_table_with_codec("test_table", Json).unwrap();
+        other.constraint(Constraint::check(|_| true)).unwrap();
+        assert_eq!(db.tables().unwrap()[1].constraints.len(), 3);
+        assert_eq!(db.tables().unwrap()[1].fingerprint.as_deref(), Some("text"));
+        drop((name, table));
+
+        assert!(matches!(
+            db.table_builder::<u64, _>("test_table", Json)
+                .fingerprint("number")
+                .open(),
+            Err(TinyBaseError::IncompatibleType { stored, .. }) if stored == "text"
+        ));
+    }
+
+    #[test]
+    fn catalog_reopen() {
+        let db = TinyBase::new(None, true);
+        let table: Table<String> = db.open_table("test_table").unwrap();
+        let name = table
+            .create_index("name", |value| value.to_owned())
+            .unwrap();
+        table.constraint(Constraint::unique(&name)).unwrap();
+        drop((name, table));
+
+        // Indexes and constraints which aren't registered again are forgotten.
+        let table: Table<String> = db.open_table("test_table").unwrap();
+        let info = &db.tables().unwrap()[0];
+        assert!(info.indexes.is_empty());
+        assert!(info.constraints.is_empty());
+
+        // They are still dropped along with the table.
+        drop(table);
+        assert!(db.drop_table("test_table").unwrap());
+        let names = db.engine.tree_names();
+        assert!(!names.iter().any(|tree| tree.starts_with(b"test_table")));
+    }
+
+    #[test]
+    fn catalog_drop_table() {
+        let db = TinyBase::new(None, true);
+        let table: Table<String> = db.open_table("test_table").unwrap();
+        table
+            .create_index("name", |value| value.to_owned())
+            .unwrap();
+        table.insert("value1".to_string()).unwrap();
+
+        assert!(matches!(
+            db.drop_table("test_table"),
+            Err(TinyBaseError::TableInUse(_))
+        ));
+        drop(table);
+
+        assert!(db.drop_table("test_table").unwrap());
+        assert!(!db.drop_table("test_table").unwrap());
+        assert!(db.tables().unwrap().is_empty());
+
+        // The table is created again from scratch, even with another type.
+        let table: Table<u64> = db.open_table("test_table").unwrap();
+        assert_eq!(table.iter().count(), 0);
+        let name = table.create_index("name", |value| *value).unwrap();
+        assert!(name.select(&1).unwrap().is_empty());
+        drop((name, table));
+
+        // Tables written before the catalog existed are dropped along with their indexes.
+        db.engine
+            .open_tree("legacy")
+            .unwrap()
+            .insert(encode(&1u64).unwrap(), encode(&"value1").unwrap())
+            .unwrap();
+        db.engine.open_tree(index_tree("legacy", "name")).unwrap();
+        assert!(db.drop_table("legacy").unwrap());
+        let names = db.engine.tree_names();
+        assert!(!names.iter().any(|tree| tree.starts_with(b"legacy")));
+        assert!(!db.drop_table("legacy").unwrap());
+    }
+
+    #[test]
+    fn catalog_rename_table() {
+        let db = TinyBase::new(None, true);
+        let table: Table<String> = db.open_table("test_table").unwrap();
+        let name = table
+            .create_index("name", |value| value.to_owned())
+            .unwrap();
+        let id = table.insert("value1".to_string()).unwrap();
+        drop((name, table));
+
+        let _: Table<String> = db.open_table("other").unwrap();
+        assert!(matches!(
+            db.rename_table("test_table", "other"),
+            Err(TinyBaseError::TableExists(_))
+        ));
+
+        // Tables written before the catalog existed aren't replaced either.
+        let legacy = db.engine.open_tree("legacy").unwrap();
+        legacy
+            .insert(encode(&1u64).unwrap(), encode(&"value1").unwrap())
+            .unwrap();
+        assert!(matches!(
+            db.rename_table("test_table", "legacy"),
+            Err(TinyBaseError::TableExists(_))
+        ));
+        assert_eq!(legacy.len(), 1);
+        assert!(matches!(
+            db.rename_table("missing", "renamed"),
+            Err(TinyBaseError::TableNotFound(_))
+        ));
+
+        db.rename_table("test_table", "renamed").unwrap();
+        let names: Vec<_> = db.tables().unwrap().into_iter().map(|t| t.name).collect();
+        assert_eq!(names, vec!["other", "renamed"]);
+
+        let table: Table<String> = db.open_table("renamed").unwrap();
+        assert_eq!(table.select(id).unwrap().unwrap().data, "value1");
+        let name = table
+            .create_index("name", |value| value.to_owned())
+            .unwrap();
+        assert_eq!(name.select(&"value1".to_string()).unwrap()[0].id, id);
+
+        let old: Table<String> = db.open_table("test_table").unwrap();
+        assert_eq!(old.iter().count(), 0);
+    }
+}
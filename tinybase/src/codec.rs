@@ -0,0 +1,223 @@
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::encoding::{decode, encode};
+use crate::result::DbResult;
+use crate::table::TableType;
+
+/// Serializes the records of a table.
+///
+/// The codec of a table is recorded when the table is created, and opening the table with a
+/// different codec fails with [`crate::result::TinyBaseError::CodecMismatch`] instead of
+/// misreading records.
+pub trait Codec: Send + Sync + 'static {
+    /// Name recorded in the catalog for tables using the codec, which must be unique.
+    fn name(&self) -> &'static str;
+
+    /// Serialize a value.
+    fn encode<T: Serialize>(&self, value: &T) -> DbResult<Vec<u8>>;
+
+    /// Deserialize a value written by [`Codec::encode`].
+    fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> DbResult<T>;
+}
+
+/// [bincode](https://docs.rs/bincode), the default codec, which is compact but only readable from
+/// Rust.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Bincode;
+
+impl Codec for Bincode {
+    fn name(&self) -> &'static str {
+        "bincode"
+    }
+
+    fn encode<T: Serialize>(&self, value: &T) -> DbResult<Vec<u8>> {
+        encode(value)
+    }
+
+    fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> DbResult<T> {
+        decode(bytes)
+    }
+}
+
+/// JSON, which is readable by anything at the cost of size.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Json;
+
+impl Codec for Json {
+    fn name(&self) -> &'static str {
+        "json"
+    }
+
+    fn encode<T: Serialize>(&self, value: &T) -> DbResult<Vec<u8>> {
+        Ok(serde_json::to_vec(value)?)
+    }
+
+    fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> DbResult<T> {
+        Ok(serde_json::from_slice(bytes)?)
+    }
+}
+
+/// [postcard](https://docs.rs/postcard), a compact format, this requires the `postcard` feature.
+#[cfg(feature = "postcard")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Postcard;
+
+#[cfg(feature = "postcard")]
+impl Codec for Postcard {
+    fn name(&self) -> &'static str {
+        "postcard"
+    }
+
+    fn encode<T: Serialize>(&self, value: &T) -> DbResult<Vec<u8>> {
+        postcard::to_allocvec(value).map_err(codec_error)
+    }
+
+    fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> DbResult<T> {
+        postcard::from_bytes(bytes).map_err(codec_error)
+    }
+}
+
+/// MessagePack, with fields stored by name, this requires the `msgpack` feature.
+#[cfg(feature = "msgpack")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MessagePack;
+
+#[cfg(feature = "msgpack")]
+impl Codec for MessagePack {
+    fn name(&self) -> &'static str {
+        "msgpack"
+    }
+
+    fn encode<T: Serialize>(&self, value: &T) -> DbResult<Vec<u8>> {
+        rmp_serde::to_vec_named(value).map_err(codec_error)
+    }
+
+    fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> DbResult<T> {
+        rmp_serde::from_slice(bytes).map_err(codec_error)
+    }
+}
+
+/// CBOR, this requires the `cbor` feature.
+#[cfg(feature = "cbor")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Cbor;
+
+#[cfg(feature = "cbor")]
+impl Codec for Cbor {
+    fn name(&self) -> &'static str {
+        "cbor"
+    }
+
+    fn encode<T: Serialize>(&self, value: &T) -> DbResult<Vec<u8>> {
+        let mut bytes = vec![];
+        ciborium::ser::into_writer(value, &mut bytes).map_err(codec_error)?;
+
+        Ok(bytes)
+    }
+
+    fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> DbResult<T> {
+        ciborium::de::from_reader(bytes).map_err(codec_error)
+    }
+}
+
+#[cfg(any(feature = "postcard", feature = "msgpack", feature = "cbor"))]
+fn codec_error(
+    err: impl std::error::Error + Send + Sync + 'static,
+) -> crate::result::TinyBaseError {
+    crate::result::TinyBaseError::Codec(Box::new(err))
+}
+
+/// A [`Codec`] for the records of a single type, so tables can hold any codec.
+pub(crate) trait RecordCodec<T>: Send + Sync {
+    fn encode(&self, value: &T) -> DbResult<Vec<u8>>;
+    fn decode(&self, bytes: &[u8]) -> DbResult<T>;
+}
+
+impl<C: Codec, T: TableType> RecordCodec<T> for C {
+    fn encode(&self, value: &T) -> DbResult<Vec<u8>> {
+        Codec::encode(self, value)
+    }
+
+    fn decode(&self, bytes: &[u8]) -> DbResult<T> {
+        Codec::decode(self, bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::result::TinyBaseError;
+    use crate::{Table, TinyBase};
+
+    #[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+    struct Person {
+        name: String,
+        age: u32,
+    }
+
+    fn round_trip(codec: impl Codec) {
+        let db = TinyBase::new(None, true);
+        let table: Table<Person> = db.open_table_with_codec("test_table", codec).unwrap();
+        let age = table.create_index("age", |person| person.age).unwrap();
+
+        let person = Person {
+            name: "John".to_string(),
+            age: 30,
+        };
+        let id = table.insert(person.clone()).unwrap();
+
+        assert_eq!(table.select(id).unwrap().unwrap().data, person);
+        assert_eq!(age.select(&30).unwrap()[0].data, person);
+    }
+
+    #[test]
+    fn codecs() {
+        round_trip(Bincode);
+        round_trip(Json);
+        #[cfg(feature = "postcard")]
+        round_trip(Postcard);
+        #[cfg(feature = "msgpack")]
+        round_trip(MessagePack);
+        #[cfg(feature = "cbor")]
+        round_trip(Cbor);
+    }
+
+    #[test]
+    fn codec_mismatch() {
+        let db = TinyBase::new(None, true);
+        let table: Table<String> = db.open_table_with_codec("test_table", Json).unwrap();
+        table.insert("value1".to_string()).unwrap();
+
+        // Records are stored as JSON after their schema version, so they are readable outside
+        // of Rust.
+        let raw = db.engine.open_tree("test_table").unwrap();
+        assert_eq!(&raw.first().unwrap().unwrap().1[4..], b"\"value1\"");
+
+        assert!(matches!(
+            db.open_table::<String>("test_table"),
+            Err(TinyBaseError::CodecMismatch { stored, requested, .. })
+                if stored == "json" && requested == "bincode"
+        ));
+
+        // Tables written before the catalog existed hold bincode records without a version.
+        let raw = db.engine.open_tree("legacy").unwrap();
+        raw.insert(encode(&1u64).unwrap(), encode(&"value1").unwrap())
+            .unwrap();
+
+        assert!(matches!(
+            db.open_table_with_codec::<String>("legacy", Json),
+            Err(TinyBaseError::CodecMismatch { stored, .. }) if stored == "bincode"
+        ));
+
+        // They are converted when first opened, and only once.
+        for _ in 0..2 {
+            let legacy: Table<String> = db.open_table("legacy").unwrap();
+            assert_eq!(legacy.select(1).unwrap().unwrap().data, "value1");
+        }
+        assert_eq!(
+            &raw.get(encode(&1u64).unwrap()).unwrap().unwrap()[..4],
+            &[0, 0, 0, 1]
+        );
+    }
+}
@@ -0,0 +1,111 @@
+use std::sync::{Condvar, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use sled::Db;
+
+use crate::result::DbResult;
+
+/// Progress of the flushes requested by durable writes.
+#[derive(Default)]
+struct State {
+    /// Number of durable writes which have requested a flush.
+    requested: u64,
+    /// Number of requests covered by a completed flush.
+    flushed: u64,
+    /// Whether a writer is currently flushing on behalf of the others.
+    flushing: bool,
+}
+
+/// Flushes the database for durable writes, sharing a single flush between concurrent writers.
+pub(crate) struct GroupCommit {
+    engine: Db,
+    /// How long the flushing writer waits for other writers to join before flushing.
+    window: Option<Duration>,
+    state: Mutex<State>,
+    flushed: Condvar,
+}
+
+impl GroupCommit {
+    pub(crate) fn new(engine: &Db, window: Option<Duration>) -> Self {
+        Self {
+            engine: engine.clone(),
+            window,
+            state: Mutex::new(State::default()),
+            flushed: Condvar::new(),
+        }
+    }
+
+    /// Block until every write made before this call has been persisted.
+    ///
+    /// The first caller flushes for everyone waiting, so concurrent durable writes are persisted
+    /// with a single flush.
+    pub(crate) fn sync(&self) -> DbResult<()> {
+        let mut state = self.state.lock().unwrap();
+        state.requested += 1;
+        let ticket = state.requested;
+
+        loop {
+            if state.flushed >= ticket {
+                return Ok(());
+            }
+
+            if state.flushing {
+                state = self.flushed.wait(state).unwrap();
+                continue;
+            }
+
+            state.flushing = true;
+            drop(state);
+
+            if let Some(window) = self.window {
+                thread::sleep(window);
+            }
+
+            // Every request made so far belongs to a write which has already completed.
+            let target = self.state.lock().unwrap().requested;
+            let result = self.engine.flush();
+
+            state = self.state.lock().unwrap();
+            state.flushing = false;
+            if result.is_ok() {
+                state.flushed = state.flushed.max(target);
+            }
+            self.flushed.notify_all();
+
+            result?;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::TinyBase;
+
+    #[test]
+    fn group_commit() {
+        let db = TinyBase::new(None, true);
+        let group = Arc::new(GroupCommit::new(
+            &db.engine,
+            Some(Duration::from_millis(10)),
+        ));
+
+        let writers: Vec<_> = (0..8)
+            .map(|_| {
+                let group = group.clone();
+                thread::spawn(move || group.sync())
+            })
+            .collect();
+
+        for writer in writers {
+            writer.join().unwrap().unwrap();
+        }
+
+        let state = group.state.lock().unwrap();
+        assert_eq!(state.flushed, 8);
+        assert!(!state.flushing);
+    }
+}
@@ -0,0 +1,387 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::encoding::encode;
+use crate::index::Index;
+use crate::result::{DbResult, TinyBaseError};
+use crate::table::Table;
+
+/// A table of JSON documents without a fixed type.
+///
+/// Dynamic tables are stored with the [`crate::codec::Json`] codec, so any table using that codec
+/// can be opened as a dynamic table, whatever type it was created with. Documents written through
+/// a dynamic table aren't checked against that type.
+///
+/// Migrations of the table aren't applied, so documents written with an older schema version are
+/// returned as they were written. Their version is given by [`Table::record_version`].
+pub type DynamicTable = Table<Value>;
+
+/// Key of an index over the documents of a [`DynamicTable`], ordered by type and then by value.
+///
+/// Integers and floats are both numbers, ordered by their value. Numbers with an integer value are
+/// always [`JsonKey::Integer`], so integers too large for a float are told apart. Documents
+/// without a value at the path of the index are indexed as [`JsonKey::Null`].
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(into = "StoredKey", from = "StoredKey")]
+pub enum JsonKey {
+    Null,
+    Bool(bool),
+    Integer(i128),
+    Number(f64),
+    String(String),
+    Array(Vec<JsonKey>),
+    Object(Vec<(String, JsonKey)>),
+}
+
+/// How a [`JsonKey`] is stored, with integers and floats sharing a variant so they are ordered
+/// together.
+///
+/// Numbers are stored as their closest float followed by their exact integer value, which orders
+/// integers that round to the same float.
+#[derive(Serialize, Deserialize)]
+enum StoredKey {
+    Null,
+    Bool(bool),
+    Number(f64, i128),
+    String(String),
+    Array(Vec<JsonKey>),
+    Object(Vec<(String, JsonKey)>),
+}
+
+impl From<JsonKey> for StoredKey {
+    fn from(key: JsonKey) -> Self {
+        match key {
+            JsonKey::Null => Self::Null,
+            JsonKey::Bool(value) => Self::Bool(value),
+            JsonKey::Integer(value) => Self::Number(value as f64, value),
+            // Saturates, so floats too large for an integer still sort after every integer.
+            JsonKey::Number(value) => Self::Number(value, value as i128),
+            JsonKey::String(value) => Self::String(value),
+            JsonKey::Array(values) => Self::Array(values),
+            JsonKey::Object(fields) => Self::Object(fields),
+        }
+    }
+}
+
+impl From<StoredKey> for JsonKey {
+    fn from(key: StoredKey) -> Self {
+        match key {
+            StoredKey::Null => Self::Null,
+            StoredKey::Bool(value) => Self::Bool(value),
+            StoredKey::Number(value, integer) if is_integer(value) => Self::Integer(integer),
+            StoredKey::Number(value, _) => Self::Number(value),
+            StoredKey::String(value) => Self::String(value),
+            StoredKey::Array(values) => Self::Array(values),
+            StoredKey::Object(fields) => Self::Object(fields),
+        }
+    }
+}
+
+impl<V: Into<Value>> From<V> for JsonKey {
+    fn from(value: V) -> Self {
+        Self::new(&value.into())
+    }
+}
+
+impl JsonKey {
+    fn new(value: &Value) -> Self {
+        match value {
+            Value::Null => Self::Null,
+            Value::Bool(value) => Self::Bool(*value),
+            Value::Number(value) => match value.as_i64() {
+                Some(integer) => Self::Integer(integer.into()),
+                None => match value.as_u64() {
+                    Some(integer) => Self::Integer(integer.into()),
+                    None => Self::float(value.as_f64().unwrap_or_default()),
+                },
+            },
+            Value::String(value) => Self::String(value.clone()),
+            Value::Array(values) => Self::Array(values.iter().map(Self::new).collect()),
+            Value::Object(fields) => Self::Object(
+                fields
+                    .iter()
+                    .map(|(name, value)| (name.clone(), Self::new(value)))
+                    .collect(),
+            ),
+        }
+    }
+
+    /// Key of a float, which is an integer if it has an integer value.
+    fn float(value: f64) -> Self {
+        if is_integer(value) {
+            // -0 is the integer 0 as well.
+            Self::Integer(value as i128)
+        } else {
+            Self::Number(value)
+        }
+    }
+}
+
+/// Check if a float has an integer value which fits in a [`JsonKey::Integer`].
+fn is_integer(value: f64) -> bool {
+    value.fract() == 0.0 && (i128::MIN as f64..i128::MAX as f64).contains(&value)
+}
+
+/// A step of a [`JsonPath`].
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    Field(String),
+    Element(usize),
+}
+
+/// Location of a value within a document, such as `$.address.city` or `$.tags[0]`.
+#[derive(Debug, Clone, PartialEq)]
+struct JsonPath(Vec<Segment>);
+
+impl JsonPath {
+    /// Parse a path made of `.field`, `["field"]` and `[index]` steps following `$`.
+    fn parse(path: &str) -> DbResult<Self> {
+        let invalid = |reason: &str| TinyBaseError::JsonPath(format!("{}: {}", path, reason));
+
+        let mut rest = path
+            .strip_prefix('$')
+            .ok_or_else(|| invalid("must start with `$`"))?;
+
+        let mut segments = vec![];
+        while !rest.is_empty() {
+            if let Some(after) = rest.strip_prefix('.') {
+                let end = after.find(['.', '[']).unwrap_or(after.len());
+                if end == 0 {
+                    return Err(invalid("empty field name"));
+                }
+
+                segments.push(Segment::Field(after[..end].to_owned()));
+                rest = &after[end..];
+            } else if let Some(after) = rest.strip_prefix('[') {
+                let end = after.find(']').ok_or_else(|| invalid("unclosed `[`"))?;
+                let step = &after[..end];
+
+                let segment = match step.strip_prefix('"').and_then(|s| s.strip_suffix('"')) {
+                    Some(field) => Segment::Field(field.to_owned()),
+                    None => Segment::Element(
+                        step.parse()
+                            .map_err(|_| invalid("expected an index or a quoted field"))?,
+                    ),
+                };
+
+                segments.push(segment);
+                rest = &after[end + 1..];
+            } else {
+                return Err(invalid("expected `.` or `[`"));
+            }
+        }
+
+        Ok(Self(segments))
+    }
+
+    /// Get the value at the path, if the document has one.
+    fn get<'a>(&self, document: &'a Value) -> Option<&'a Value> {
+        self.0
+            .iter()
+            .try_fold(document, |value, segment| match segment {
+                Segment::Field(name) => value.get(name),
+                Segment::Element(index) => value.get(index),
+            })
+    }
+}
+
+impl Table<Value> {
+    /// Get the schema version a document was written with.
+    ///
+    /// Documents aren't migrated when read through a dynamic table, so documents of an older
+    /// version have the fields of that version.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The ID of the document.
+    ///
+    /// # Returns
+    ///
+    /// The version of the document, or [`None`] if there is no document with that ID.
+    pub fn record_version(&self, id: u64) -> DbResult<Option<u32>> {
+        match self.shared.root.read().unwrap().get(encode(&id)?)? {
+            Some(bytes) => Ok(Some(self.schema.record_version(&bytes)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Create an index over the value at a JSON path of each document.
+    ///
+    /// The index is named after the path, and can be used in queries like any other index.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path of the indexed value, such as `$.address.city`.
+    ///
+    /// # Returns
+    ///
+    /// The created index, or [`TinyBaseError::JsonPath`] if the path is invalid.
+    pub fn create_path_index(&self, path: &str) -> DbResult<Index<Value, JsonKey>> {
+        let parsed = JsonPath::parse(path)?;
+
+        self.create_index(path, move |document| {
+            parsed.get(document).map_or(JsonKey::Null, JsonKey::new)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::codec::Json;
+    use crate::{ConditionBuilder, Order, QueryBuilder, TinyBase};
+
+    #[derive(Serialize, Deserialize, Debug, Clone)]
+    struct Person {
+        name: String,
+        age: u32,
+    }
+
+    #[test]
+    fn json_path() {
+        let document = json!({"address": {"city": "Paris"}, "tags": ["a", "b"], "a.b": 1});
+
+        let get = |path| JsonPath::parse(path).unwrap().get(&document).cloned();
+        assert_eq!(get("$"), Some(document.clone()));
+        assert_eq!(get("$.address.city"), Some(json!("Paris")));
+        assert_eq!(get("$.tags[1]"), Some(json!("b")));
+        assert_eq!(get("$[\"a.b\"]"), Some(json!(1)));
+        assert_eq!(get("$.address.street"), None);
+
+        for path in ["address", "$.", "$..city", "$[0", "$[x]", "$x"] {
+            assert!(matches!(
+                JsonPath::parse(path),
+                Err(TinyBaseError::JsonPath(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn dynamic_table() {
+        let db = TinyBase::new(None, true);
+        let table = db.open_dynamic_table("test_table").unwrap();
+        let city = table.create_path_index("$.address.city").unwrap();
+        let age = table.create_path_index("$.age").unwrap();
+
+        let paris = table
+            .insert(json!({"name": "John", "age": 30, "address": {"city": "Paris"}}))
+            .unwrap();
+        table
+            .insert(json!({"name": "Jane", "age": 25, "address": {"city": "London"}}))
+            .unwrap();
+        let homeless = table.insert(json!({"name": "Joe", "age": 40})).unwrap();
+
+        assert_eq!(city.select(&"Paris".into()).unwrap()[0].id, paris);
+        assert_eq!(city.select(&JsonKey::Null).unwrap()[0].id, homeless);
+
+        let older = QueryBuilder::new(&table)
+            .with_condition(ConditionBuilder::and(
+                ConditionBuilder::filter(|document: &Value| document["age"].as_u64() > Some(28)),
+                ConditionBuilder::not(ConditionBuilder::by(&city, "London".into())),
+            ))
+            .order_by(&age, Order::Desc)
+            .select()
+            .unwrap();
+        let names: Vec<_> = older.iter().map(|r| r.data["name"].clone()).collect();
+        assert_eq!(names, vec![json!("Joe"), json!("John")]);
+
+        assert_eq!(age.range(JsonKey::from(26)..).unwrap().len(), 2);
+        assert_eq!(age.max().unwrap(), Some(JsonKey::Integer(40)));
+    }
+
+    #[test]
+    fn dynamic_number_keys() {
+        let db = TinyBase::new(None, true);
+        let table = db.open_dynamic_table("test_table").unwrap();
+        let number = table.create_path_index("$.n").unwrap();
+
+        let values = [
+            json!(u64::MAX),
+            json!(9007199254740993u64),
+            json!(9007199254740992u64),
+            json!(2),
+            json!(1.5),
+            json!(-0.0),
+            json!(-1),
+        ];
+        for value in &values {
+            table.insert(json!({ "n": value })).unwrap();
+        }
+
+        // Integers too large for a float aren't mixed up.
+        let large = number.select(&JsonKey::from(9007199254740993u64)).unwrap();
+        assert_eq!(large.len(), 1);
+        assert_eq!(large[0].data["n"], json!(9007199254740993u64));
+
+        // Integers and floats are ordered by value, with -0 being 0.
+        assert_eq!(number.select(&JsonKey::Integer(0)).unwrap().len(), 1);
+        assert_eq!(number.select(&JsonKey::from(2.0)).unwrap().len(), 1);
+        let ordered: Vec<_> = number
+            .range(..)
+            .unwrap()
+            .into_iter()
+            .rev()
+            .map(|r| r.data["n"].clone())
+            .collect();
+        assert_eq!(ordered, values);
+        assert_eq!(
+            number
+                .range(JsonKey::from(1)..JsonKey::from(2))
+                .unwrap()
+                .len(),
+            1
+        );
+        assert_eq!(
+            number.max().unwrap(),
+            Some(JsonKey::Integer(u64::MAX.into()))
+        );
+    }
+
+    #[test]
+    fn dynamic_typed_table() {
+        let db = TinyBase::new(None, true);
+        let typed: Table<Person> = db.open_table_with_codec("people", Json).unwrap();
+        let id = typed
+            .insert(Person {
+                name: "John".to_string(),
+                age: 30,
+            })
+            .unwrap();
+
+        // Tables of any type can be read without it, as long as they are stored as JSON.
+        let table = db.open_dynamic_table("people").unwrap();
+        let name = table.create_path_index("$.name").unwrap();
+        assert_eq!(name.select(&"John".into()).unwrap()[0].id, id);
+        assert_eq!(table.select(id).unwrap().unwrap().data["age"], json!(30));
+
+        // Opening the table dynamically doesn't change its type.
+        assert!(db.open_table_with_codec::<Person>("people", Json).is_ok());
+        drop((name, table));
+
+        // Documents of older versions are returned as they were written, along with their version.
+        let typed: Table<Person> = db
+            .table_builder("people", Json)
+            .version(2)
+            .migration(1, |person: Person| person)
+            .open()
+            .unwrap();
+        let new = typed
+            .insert(Person {
+                name: "Jane".to_string(),
+                age: 25,
+            })
+            .unwrap();
+        let table = db.open_dynamic_table("people").unwrap();
+        assert_eq!(table.record_version(id).unwrap(), Some(1));
+        assert_eq!(table.record_version(new).unwrap(), Some(2));
+        assert_eq!(table.record_version(new + 1).unwrap(), None);
+
+        let _: Table<String> = db.open_table("bincode").unwrap();
+        assert!(matches!(
+            db.open_dynamic_table("bincode"),
+            Err(TinyBaseError::CodecMismatch { .. })
+        ));
+    }
+}
@@ -2,6 +2,9 @@ use bincode::Options;
 
 use crate::DbResult;
 
+mod key;
+pub(crate) use key::{decode_key, encode_key};
+
 pub(crate) fn encode<S: ?Sized + serde::Serialize>(item: &S) -> DbResult<Vec<u8>> {
     Ok(bincode::DefaultOptions::new()
         .with_big_endian()
@@ -0,0 +1,840 @@
+//! Order-preserving ("memcomparable") encoding for index keys.
+//!
+//! sled sorts keys by comparing their raw bytes, so index keys are encoded such that the
+//! lexicographic order of the encoded bytes matches the [`Ord`] order of the key type:
+//!
+//! * Unsigned integers are written big-endian.
+//! * Signed integers are written big-endian with the sign bit flipped.
+//! * Floats have the sign bit flipped when positive and all bits flipped when negative. `-0.0` is
+//!   stored as `0.0`, and NaN is rejected.
+//! * Strings and byte arrays escape `0x00` as `0x00 0xFF` and are terminated by `0x00 0x00`.
+//! * Options are prefixed by `0x00` for `None` and `0x01` for `Some`.
+//! * Sequences and maps prefix each element with `0x01` and are terminated by `0x00`.
+//! * Enum variants are prefixed by their variant index.
+//! * Tuples and structs are the concatenation of their fields.
+
+use std::fmt::Display;
+
+use serde::de::{self, DeserializeSeed, IntoDeserializer, SeqAccess, VariantAccess, Visitor};
+use serde::ser::{self, Serialize};
+
+use crate::DbResult;
+
+/// Error produced while encoding or decoding an index key.
+#[derive(Debug)]
+pub(crate) struct Error(String);
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl ser::Error for Error {
+    fn custom<M: Display>(msg: M) -> Self {
+        Self(msg.to_string())
+    }
+}
+
+impl de::Error for Error {
+    fn custom<M: Display>(msg: M) -> Self {
+        Self(msg.to_string())
+    }
+}
+
+impl From<Error> for crate::result::TinyBaseError {
+    fn from(err: Error) -> Self {
+        Self::KeyEncoding(Box::new(err))
+    }
+}
+
+type Result<T> = std::result::Result<T, Error>;
+
+const ESCAPE: u8 = 0x00;
+const ESCAPED_ZERO: u8 = 0xFF;
+const TERMINATOR: u8 = 0x00;
+
+const NONE: u8 = 0x00;
+const SOME: u8 = 0x01;
+
+const SEQ_END: u8 = 0x00;
+const SEQ_ELEMENT: u8 = 0x01;
+
+/// Encode a value into an order-preserving byte representation.
+pub(crate) fn encode_key<S: ?Sized + Serialize>(item: &S) -> DbResult<Vec<u8>> {
+    let mut serializer = KeySerializer { output: vec![] };
+    item.serialize(&mut serializer)?;
+    Ok(serializer.output)
+}
+
+/// Decode a value previously encoded by [`encode_key`].
+pub(crate) fn decode_key<'a, T: de::Deserialize<'a>>(bytes: &'a [u8]) -> DbResult<T> {
+    let mut deserializer = KeyDeserializer { input: bytes };
+    let value = T::deserialize(&mut deserializer)?;
+
+    if deserializer.input.is_empty() {
+        Ok(value)
+    } else {
+        Err(Error("trailing bytes after key".into()).into())
+    }
+}
+
+struct KeySerializer {
+    output: Vec<u8>,
+}
+
+impl KeySerializer {
+    fn write_bytes(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.output.push(*byte);
+            if *byte == ESCAPE {
+                self.output.push(ESCAPED_ZERO);
+            }
+        }
+
+        self.output.extend_from_slice(&[ESCAPE, TERMINATOR]);
+    }
+}
+
+impl ser::Serializer for &mut KeySerializer {
+    type Ok = ();
+    type Error = Error;
+
+    type SerializeSeq = Self;
+    type SerializeTuple = Self;
+    type SerializeTupleStruct = Self;
+    type SerializeTupleVariant = Self;
+    type SerializeMap = Self;
+    type SerializeStruct = Self;
+    type SerializeStructVariant = Self;
+
+    fn serialize_bool(self, v: bool) -> Result<()> {
+        self.output.push(v as u8);
+        Ok(())
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<()> {
+        self.output.push((v as u8) ^ (1 << 7));
+        Ok(())
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<()> {
+        self.output
+            .extend_from_slice(&((v as u16) ^ (1 << 15)).to_be_bytes());
+        Ok(())
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<()> {
+        self.output
+            .extend_from_slice(&((v as u32) ^ (1 << 31)).to_be_bytes());
+        Ok(())
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<()> {
+        self.output
+            .extend_from_slice(&((v as u64) ^ (1 << 63)).to_be_bytes());
+        Ok(())
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<()> {
+        self.output
+            .extend_from_slice(&((v as u128) ^ (1 << 127)).to_be_bytes());
+        Ok(())
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<()> {
+        self.output.push(v);
+        Ok(())
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<()> {
+        self.output.extend_from_slice(&v.to_be_bytes());
+        Ok(())
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<()> {
+        self.output.extend_from_slice(&v.to_be_bytes());
+        Ok(())
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<()> {
+        self.output.extend_from_slice(&v.to_be_bytes());
+        Ok(())
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<()> {
+        self.output.extend_from_slice(&v.to_be_bytes());
+        Ok(())
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<()> {
+        if v.is_nan() {
+            return Err(Error("NaN has no place in the order of keys".into()));
+        }
+
+        // Both zeros are equal, so they are stored as the same key.
+        let bits = if v == 0.0 { 0 } else { v.to_bits() };
+        let bits = if bits >> 31 == 1 {
+            !bits
+        } else {
+            bits ^ (1 << 31)
+        };
+        self.output.extend_from_slice(&bits.to_be_bytes());
+        Ok(())
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<()> {
+        if v.is_nan() {
+            return Err(Error("NaN has no place in the order of keys".into()));
+        }
+
+        // Both zeros are equal, so they are stored as the same key.
+        let bits = if v == 0.0 { 0 } else { v.to_bits() };
+        let bits = if bits >> 63 == 1 {
+            !bits
+        } else {
+            bits ^ (1 << 63)
+        };
+        self.output.extend_from_slice(&bits.to_be_bytes());
+        Ok(())
+    }
+
+    fn serialize_char(self, v: char) -> Result<()> {
+        self.serialize_u32(v as u32)
+    }
+
+    fn serialize_str(self, v: &str) -> Result<()> {
+        self.write_bytes(v.as_bytes());
+        Ok(())
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<()> {
+        self.write_bytes(v);
+        Ok(())
+    }
+
+    fn serialize_none(self) -> Result<()> {
+        self.output.push(NONE);
+        Ok(())
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<()> {
+        self.output.push(SOME);
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<()> {
+        Ok(())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<()> {
+        Ok(())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        _variant: &'static str,
+    ) -> Result<()> {
+        self.serialize_u32(variant_index)
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        _variant: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        self.serialize_u32(variant_index)?;
+        value.serialize(self)
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq> {
+        Ok(self)
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple> {
+        Ok(self)
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct> {
+        Ok(self)
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant> {
+        self.serialize_u32(variant_index)?;
+        Ok(self)
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap> {
+        Ok(self)
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct> {
+        Ok(self)
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant> {
+        self.serialize_u32(variant_index)?;
+        Ok(self)
+    }
+}
+
+impl ser::SerializeSeq for &mut KeySerializer {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        self.output.push(SEQ_ELEMENT);
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<()> {
+        self.output.push(SEQ_END);
+        Ok(())
+    }
+}
+
+impl ser::SerializeMap for &mut KeySerializer {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_key<T: ?Sized + Serialize>(&mut self, key: &T) -> Result<()> {
+        self.output.push(SEQ_ELEMENT);
+        key.serialize(&mut **self)
+    }
+
+    fn serialize_value<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<()> {
+        self.output.push(SEQ_END);
+        Ok(())
+    }
+}
+
+impl ser::SerializeTuple for &mut KeySerializer {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeTupleStruct for &mut KeySerializer {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeTupleVariant for &mut KeySerializer {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeStruct for &mut KeySerializer {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        _key: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeStructVariant for &mut KeySerializer {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        _key: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+struct KeyDeserializer<'de> {
+    input: &'de [u8],
+}
+
+impl<'de> KeyDeserializer<'de> {
+    fn take<const N: usize>(&mut self) -> Result<[u8; N]> {
+        if self.input.len() < N {
+            return Err(Error("unexpected end of key".into()));
+        }
+
+        let (bytes, rest) = self.input.split_at(N);
+        self.input = rest;
+        Ok(bytes.try_into().unwrap())
+    }
+
+    fn take_byte(&mut self) -> Result<u8> {
+        Ok(self.take::<1>()?[0])
+    }
+
+    fn read_bytes(&mut self) -> Result<Vec<u8>> {
+        let mut bytes = vec![];
+
+        loop {
+            match self.take_byte()? {
+                ESCAPE => match self.take_byte()? {
+                    TERMINATOR => return Ok(bytes),
+                    ESCAPED_ZERO => bytes.push(0),
+                    _ => return Err(Error("invalid escape sequence in key".into())),
+                },
+                byte => bytes.push(byte),
+            }
+        }
+    }
+
+    fn read_string(&mut self) -> Result<String> {
+        String::from_utf8(self.read_bytes()?).map_err(|err| Error(err.to_string()))
+    }
+}
+
+impl<'de> de::Deserializer<'de> for &mut KeyDeserializer<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value> {
+        Err(Error("key encoding is not self-describing".into()))
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_bool(self.take_byte()? != 0)
+    }
+
+    fn deserialize_i8<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_i8((self.take_byte()? ^ (1 << 7)) as i8)
+    }
+
+    fn deserialize_i16<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_i16((u16::from_be_bytes(self.take()?) ^ (1 << 15)) as i16)
+    }
+
+    fn deserialize_i32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_i32((u32::from_be_bytes(self.take()?) ^ (1 << 31)) as i32)
+    }
+
+    fn deserialize_i64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_i64((u64::from_be_bytes(self.take()?) ^ (1 << 63)) as i64)
+    }
+
+    fn deserialize_i128<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_i128((u128::from_be_bytes(self.take()?) ^ (1 << 127)) as i128)
+    }
+
+    fn deserialize_u8<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_u8(self.take_byte()?)
+    }
+
+    fn deserialize_u16<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_u16(u16::from_be_bytes(self.take()?))
+    }
+
+    fn deserialize_u32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_u32(u32::from_be_bytes(self.take()?))
+    }
+
+    fn deserialize_u64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_u64(u64::from_be_bytes(self.take()?))
+    }
+
+    fn deserialize_u128<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_u128(u128::from_be_bytes(self.take()?))
+    }
+
+    fn deserialize_f32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let bits = u32::from_be_bytes(self.take()?);
+        let bits = if bits >> 31 == 1 {
+            bits ^ (1 << 31)
+        } else {
+            !bits
+        };
+        visitor.visit_f32(f32::from_bits(bits))
+    }
+
+    fn deserialize_f64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let bits = u64::from_be_bytes(self.take()?);
+        let bits = if bits >> 63 == 1 {
+            bits ^ (1 << 63)
+        } else {
+            !bits
+        };
+        visitor.visit_f64(f64::from_bits(bits))
+    }
+
+    fn deserialize_char<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let code = u32::from_be_bytes(self.take()?);
+        visitor.visit_char(char::from_u32(code).ok_or_else(|| Error("invalid char in key".into()))?)
+    }
+
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_string(self.read_string()?)
+    }
+
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_string(self.read_string()?)
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_byte_buf(self.read_bytes()?)
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_byte_buf(self.read_bytes()?)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self.take_byte()? {
+            NONE => visitor.visit_none(),
+            SOME => visitor.visit_some(self),
+            _ => Err(Error("invalid option tag in key".into())),
+        }
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_seq(Elements { de: self })
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value> {
+        visitor.visit_seq(Fields { de: self, len })
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_seq(Fields { de: self, len })
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_map(Elements { de: self })
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_seq(Fields {
+            de: self,
+            len: fields.len(),
+        })
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_enum(self)
+    }
+
+    fn deserialize_identifier<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value> {
+        Err(Error("key encoding does not store identifiers".into()))
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value> {
+        Err(Error("key encoding is not self-describing".into()))
+    }
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+}
+
+/// Access to a fixed number of fields (tuples and structs).
+struct Fields<'a, 'de> {
+    de: &'a mut KeyDeserializer<'de>,
+    len: usize,
+}
+
+impl<'de, 'a> SeqAccess<'de> for Fields<'a, 'de> {
+    type Error = Error;
+
+    fn next_element_seed<S: DeserializeSeed<'de>>(&mut self, seed: S) -> Result<Option<S::Value>> {
+        if self.len == 0 {
+            return Ok(None);
+        }
+
+        self.len -= 1;
+        seed.deserialize(&mut *self.de).map(Some)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.len)
+    }
+}
+
+/// Access to a variable number of elements (sequences and maps).
+struct Elements<'a, 'de> {
+    de: &'a mut KeyDeserializer<'de>,
+}
+
+impl<'a, 'de> Elements<'a, 'de> {
+    fn has_next(&mut self) -> Result<bool> {
+        match self.de.take_byte()? {
+            SEQ_ELEMENT => Ok(true),
+            SEQ_END => Ok(false),
+            _ => Err(Error("invalid sequence tag in key".into())),
+        }
+    }
+}
+
+impl<'de, 'a> SeqAccess<'de> for Elements<'a, 'de> {
+    type Error = Error;
+
+    fn next_element_seed<S: DeserializeSeed<'de>>(&mut self, seed: S) -> Result<Option<S::Value>> {
+        if self.has_next()? {
+            seed.deserialize(&mut *self.de).map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+impl<'de, 'a> de::MapAccess<'de> for Elements<'a, 'de> {
+    type Error = Error;
+
+    fn next_key_seed<S: DeserializeSeed<'de>>(&mut self, seed: S) -> Result<Option<S::Value>> {
+        if self.has_next()? {
+            seed.deserialize(&mut *self.de).map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn next_value_seed<S: DeserializeSeed<'de>>(&mut self, seed: S) -> Result<S::Value> {
+        seed.deserialize(&mut *self.de)
+    }
+}
+
+impl<'de> de::EnumAccess<'de> for &mut KeyDeserializer<'de> {
+    type Error = Error;
+    type Variant = Self;
+
+    fn variant_seed<S: DeserializeSeed<'de>>(self, seed: S) -> Result<(S::Value, Self)> {
+        let variant_index = u32::from_be_bytes(self.take()?);
+        let value = seed.deserialize(variant_index.into_deserializer())?;
+        Ok((value, self))
+    }
+}
+
+impl<'de> VariantAccess<'de> for &mut KeyDeserializer<'de> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<()> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<S: DeserializeSeed<'de>>(self, seed: S) -> Result<S::Value> {
+        seed.deserialize(self)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value> {
+        de::Deserializer::deserialize_tuple(self, len, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        de::Deserializer::deserialize_tuple(self, fields.len(), visitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use serde::{Deserialize, Serialize};
+
+    /// Assert that the encoded keys sort in the same order as the values themselves.
+    fn assert_ordered<T: Serialize + Ord + std::fmt::Debug>(mut values: Vec<T>) {
+        values.sort();
+
+        let encoded: Vec<Vec<u8>> = values.iter().map(|v| encode_key(v).unwrap()).collect();
+        for pair in encoded.windows(2) {
+            assert!(pair[0] < pair[1], "{:?} is not ordered", values);
+        }
+    }
+
+    #[test]
+    fn key_order_signed() {
+        assert_ordered(vec![i64::MIN, -1000, -1, 0, 1, 1000, i64::MAX]);
+        assert_ordered(vec![i8::MIN, -1, 0, 1, i8::MAX]);
+    }
+
+    #[test]
+    fn key_order_float() {
+        let values = [
+            f64::NEG_INFINITY,
+            -10.5,
+            -0.25,
+            0.0,
+            0.25,
+            10.5,
+            f64::INFINITY,
+        ];
+
+        let encoded: Vec<Vec<u8>> = values.iter().map(|v| encode_key(v).unwrap()).collect();
+        for pair in encoded.windows(2) {
+            assert!(pair[0] < pair[1]);
+        }
+
+        // Negative zero is equal to zero, so it is the same key.
+        assert_eq!(encode_key(&-0.0f64).unwrap(), encode_key(&0.0f64).unwrap());
+        assert_eq!(encode_key(&-0.0f32).unwrap(), encode_key(&0.0f32).unwrap());
+        assert!(matches!(
+            encode_key(&f64::NAN),
+            Err(crate::result::TinyBaseError::KeyEncoding(_))
+        ));
+        assert!(matches!(
+            encode_key(&f32::NAN),
+            Err(crate::result::TinyBaseError::KeyEncoding(_))
+        ));
+    }
+
+    #[test]
+    fn key_order_string() {
+        assert_ordered(vec![
+            "".to_string(),
+            "a".to_string(),
+            "a\0".to_string(),
+            "a\0b".to_string(),
+            "a\x01".to_string(),
+            "ab".to_string(),
+            "b".to_string(),
+            "ba".to_string(),
+        ]);
+    }
+
+    #[test]
+    fn key_order_composite() {
+        assert_ordered(vec![None, Some(-5), Some(0), Some(3)]);
+        assert_ordered(vec![
+            ("a".to_string(), 2),
+            ("a".to_string(), 10),
+            ("ab".to_string(), -1),
+            ("b".to_string(), 0),
+        ]);
+        assert_ordered(vec![vec![], vec![1u32], vec![1, 2], vec![2]]);
+    }
+
+    #[test]
+    fn key_roundtrip() {
+        #[derive(Serialize, Deserialize, Debug, PartialEq)]
+        enum Kind {
+            Unit,
+            Tuple(i32, String),
+            Struct { value: Option<u8> },
+        }
+
+        #[derive(Serialize, Deserialize, Debug, PartialEq)]
+        struct Key {
+            name: String,
+            score: f32,
+            kinds: Vec<Kind>,
+        }
+
+        let key = Key {
+            name: "some\0name".to_string(),
+            score: -2.5,
+            kinds: vec![
+                Kind::Unit,
+                Kind::Tuple(-3, "x".to_string()),
+                Kind::Struct { value: Some(8) },
+            ],
+        };
+
+        let encoded = encode_key(&key).unwrap();
+        assert_eq!(decode_key::<Key>(&encoded).unwrap(), key);
+    }
+}
@@ -0,0 +1,473 @@
+use std::io::{BufRead, BufReader, Read, Write};
+
+use crate::record::Record;
+use crate::result::{DbResult, TinyBaseError};
+use crate::table::{TableInner, TableType};
+
+/// How the IDs of imported records are assigned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportIds {
+    /// Keep the ID of each record, failing records whose ID is already taken.
+    Preserve,
+    /// Give each record a new ID, as if it was inserted.
+    Regenerate,
+}
+
+/// A record which could not be imported.
+#[derive(Debug)]
+pub struct ImportFailure {
+    /// Line number of the record, starting at one.
+    pub line: usize,
+    pub error: TinyBaseError,
+}
+
+/// Outcome of an import, which carries on past records that fail.
+#[derive(Debug, Default)]
+pub struct ImportReport {
+    /// IDs of the imported records, in the order they were read.
+    pub imported: Vec<u64>,
+    pub failures: Vec<ImportFailure>,
+}
+
+impl<T> TableInner<T>
+where
+    T: TableType,
+{
+    /// Write every record of the table as newline delimited JSON, one [`Record`] per line,
+    /// ordered by ID.
+    ///
+    /// # Arguments
+    ///
+    /// * `writer` - Where to write the records.
+    ///
+    /// # Returns
+    ///
+    /// The number of records written.
+    pub fn export_json(&self, mut writer: impl Write) -> DbResult<usize> {
+        let mut count = 0;
+        for record in self.iter() {
+            serde_json::to_writer(&mut writer, &record?)?;
+            writer.write_all(b"\n")?;
+            count += 1;
+        }
+
+        writer.flush()?;
+
+        Ok(count)
+    }
+
+    /// Import records from newline delimited JSON, one [`Record`] per line as written by
+    /// [`TableInner::export_json`]. Empty lines are skipped.
+    ///
+    /// Each record is inserted on its own, so it is checked against the constraints of the table
+    /// and added to its indexes. Records which fail don't stop the import, they are reported
+    /// along with their line instead.
+    ///
+    /// # Arguments
+    ///
+    /// * `reader` - Where to read the records from.
+    /// * `ids` - Whether to keep the ID of each record or generate a new one.
+    ///
+    /// # Returns
+    ///
+    /// An [`ImportReport`] of the imported and failed records, or an error if reading fails.
+    pub fn import_json(&self, reader: impl Read, ids: ImportIds) -> DbResult<ImportReport> {
+        let mut report = ImportReport::default();
+
+        for (index, line) in BufReader::new(reader).lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let result = serde_json::from_str::<Record<T>>(&line)
+                .map_err(TinyBaseError::from)
+                .and_then(|record| self.import_record(record, ids));
+
+            match result {
+                Ok(id) => report.imported.push(id),
+                Err(error) => report.failures.push(ImportFailure {
+                    line: index + 1,
+                    error,
+                }),
+            }
+        }
+
+        Ok(report)
+    }
+
+    /// Insert an imported record.
+    ///
+    /// # Returns
+    ///
+    /// The ID of the record in the table.
+    pub(crate) fn import_record(&self, record: Record<T>, ids: ImportIds) -> DbResult<u64> {
+        match ids {
+            ImportIds::Preserve => {
+                let id = record.id;
+                self.insert_with_id(record)?;
+                Ok(id)
+            }
+            ImportIds::Regenerate => self.insert(record.data),
+        }
+    }
+
+    /// Write every record of the table as CSV, ordered by ID.
+    ///
+    /// The first column holds the ID, followed by a column for each field of the record named
+    /// after its serde field name. Records must be flat structs, as nested values can't be
+    /// written to a column. The header is written even if the table is empty, so the result can
+    /// always be imported again.
+    ///
+    /// # Arguments
+    ///
+    /// * `writer` - Where to write the records.
+    ///
+    /// # Returns
+    ///
+    /// The number of records written.
+    #[cfg(feature = "csv")]
+    pub fn export_csv(&self, writer: impl Write) -> DbResult<usize> {
+        let mut writer = csv::WriterBuilder::new()
+            .has_headers(false)
+            .from_writer(writer);
+
+        let mut count = 0;
+        for record in self.iter() {
+            let record = record?;
+
+            if count == 0 {
+                writer.write_field(ID_COLUMN)?;
+                writer.write_record(&csv_headers(&record.data)?)?;
+            }
+
+            writer.write_field(record.id.to_string())?;
+            writer.serialize(&record.data)?;
+            count += 1;
+        }
+
+        if count == 0 {
+            writer.write_field(ID_COLUMN)?;
+            writer.write_record(struct_fields::<T>())?;
+        }
+
+        writer.flush()?;
+
+        Ok(count)
+    }
+
+    /// Import records from CSV as written by [`TableInner::export_csv`].
+    ///
+    /// Columns are matched to fields by their header, the first column must be the ID. Each
+    /// record is inserted on its own, so constraint violations and malformed rows are reported
+    /// along with their line instead of stopping the import.
+    ///
+    /// # Arguments
+    ///
+    /// * `reader` - Where to read the records from.
+    /// * `ids` - Whether to keep the ID of each record or generate a new one.
+    ///
+    /// # Returns
+    ///
+    /// An [`ImportReport`] of the imported and failed records, or an error if reading fails or
+    /// the header is invalid.
+    #[cfg(feature = "csv")]
+    pub fn import_csv(&self, reader: impl Read, ids: ImportIds) -> DbResult<ImportReport> {
+        let mut reader = csv::Reader::from_reader(reader);
+
+        let headers = reader.headers()?.clone();
+        if headers.get(0) != Some(ID_COLUMN) {
+            return Err(TinyBaseError::CsvHeader(format!(
+                "the first column must be `{}`",
+                ID_COLUMN
+            )));
+        }
+        let fields: csv::StringRecord = headers.iter().skip(1).collect();
+
+        let mut report = ImportReport::default();
+        for row in reader.records() {
+            let row = match row {
+                Ok(row) => row,
+                Err(err) if matches!(err.kind(), csv::ErrorKind::Io(_)) => return Err(err.into()),
+                Err(err) => {
+                    let line = err
+                        .position()
+                        .map_or(0, |position| position.line() as usize);
+                    report.failures.push(ImportFailure {
+                        line,
+                        error: err.into(),
+                    });
+                    continue;
+                }
+            };
+            let line = row
+                .position()
+                .map_or(0, |position| position.line() as usize);
+
+            let result =
+                csv_record(&row, &fields).and_then(|record| self.import_record(record, ids));
+
+            match result {
+                Ok(id) => report.imported.push(id),
+                Err(error) => report.failures.push(ImportFailure { line, error }),
+            }
+        }
+
+        Ok(report)
+    }
+}
+
+/// Header of the ID column in CSV.
+#[cfg(feature = "csv")]
+const ID_COLUMN: &str = "id";
+
+/// Get the CSV headers of a record type, from the serde field names of a value.
+#[cfg(feature = "csv")]
+fn csv_headers<T: TableType>(data: &T) -> DbResult<csv::StringRecord> {
+    let mut writer = csv::Writer::from_writer(vec![]);
+    writer.serialize(data)?;
+    let written = writer
+        .into_inner()
+        .map_err(|err| TinyBaseError::Io(err.into_error()))?;
+
+    Ok(csv::Reader::from_reader(written.as_slice())
+        .headers()?
+        .clone())
+}
+
+/// Get the serde field names of a struct type, without a value of it.
+///
+/// # Returns
+///
+/// The names of the fields, or none if the type isn't deserialized as a struct.
+#[cfg(feature = "csv")]
+fn struct_fields<T: TableType>() -> &'static [&'static str] {
+    let mut fields = FieldNames(&[]);
+    let _ = T::deserialize(&mut fields);
+    fields.0
+}
+
+/// Deserializer which records the field names of the struct being deserialized and then fails.
+#[cfg(feature = "csv")]
+struct FieldNames(&'static [&'static str]);
+
+#[cfg(feature = "csv")]
+impl<'de> serde::Deserializer<'de> for &mut FieldNames {
+    type Error = serde::de::value::Error;
+
+    fn deserialize_any<V: serde::de::Visitor<'de>>(self, _: V) -> Result<V::Value, Self::Error> {
+        Err(serde::de::Error::custom("not a struct"))
+    }
+
+    fn deserialize_struct<V: serde::de::Visitor<'de>>(
+        self,
+        _: &'static str,
+        fields: &'static [&'static str],
+        _: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.0 = fields;
+        Err(serde::de::Error::custom("fields recorded"))
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf
+        option unit unit_struct newtype_struct seq tuple tuple_struct map enum identifier
+        ignored_any
+    }
+}
+
+/// Read a record from a CSV row, the ID being in the first column.
+#[cfg(feature = "csv")]
+fn csv_record<T: TableType>(
+    row: &csv::StringRecord,
+    fields: &csv::StringRecord,
+) -> DbResult<Record<T>> {
+    let id: csv::StringRecord = row.iter().take(1).collect();
+    let data: csv::StringRecord = row.iter().skip(1).collect();
+
+    Ok(Record {
+        id: id.deserialize::<(u64,)>(None)?.0,
+        data: data.deserialize(Some(fields))?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Constraint, Table, TinyBase};
+
+    #[test]
+    fn json_export_import() {
+        let db = TinyBase::new(None, true);
+        let table: Table<String> = db.open_table("test_table").unwrap();
+        let id1 = table.insert("value1".to_string()).unwrap();
+        let id2 = table.insert("value2".to_string()).unwrap();
+
+        let mut exported = vec![];
+        assert_eq!(table.export_json(&mut exported).unwrap(), 2);
+        assert_eq!(
+            String::from_utf8(exported.clone()).unwrap(),
+            format!(
+                "{{\"id\":{},\"data\":\"value1\"}}\n{{\"id\":{},\"data\":\"value2\"}}\n",
+                id1, id2
+            )
+        );
+
+        let copy: Table<String> = db.open_table("copy").unwrap();
+        let name = copy.create_index("name", |value| value.to_owned()).unwrap();
+        copy.constraint(Constraint::unique(&name)).unwrap();
+
+        let report = copy
+            .import_json(exported.as_slice(), ImportIds::Preserve)
+            .unwrap();
+        assert_eq!(report.imported, vec![id1, id2]);
+        assert!(report.failures.is_empty());
+        assert_eq!(name.select(&"value2".to_string()).unwrap()[0].id, id2);
+
+        // Preserved IDs are never generated again.
+        assert!(copy.insert("value3".to_string()).unwrap() > id2);
+
+        let input = format!(
+            "{{\"id\":{},\"data\":\"value4\"}}\n\nnot json\n{{\"id\":1,\"data\":\"value1\"}}\n",
+            id1
+        );
+        let report = copy
+            .import_json(input.as_bytes(), ImportIds::Regenerate)
+            .unwrap();
+
+        assert_eq!(report.imported.len(), 1);
+        assert_ne!(report.imported[0], id1);
+        assert_eq!(report.failures.len(), 2);
+        assert_eq!(report.failures[0].line, 3);
+        assert!(matches!(report.failures[0].error, TinyBaseError::Json(_)));
+        assert_eq!(report.failures[1].line, 4);
+        assert!(matches!(
+            report.failures[1].error,
+            TinyBaseError::Exists { .. }
+        ));
+
+        let report = copy
+            .import_json(input.as_bytes(), ImportIds::Preserve)
+            .unwrap();
+        assert!(matches!(
+            report.failures[0].error,
+            TinyBaseError::IdExists(id) if id == id1
+        ));
+
+        // The largest ID fails its line, and records failing their constraints reserve no ID.
+        let ahead = copy.insert("value5".to_string()).unwrap() + 1000;
+        let input = format!(
+            "{{\"id\":{},\"data\":\"value1\"}}\n{{\"id\":{},\"data\":\"max\"}}\n",
+            ahead,
+            u64::MAX,
+        );
+        let report = copy
+            .import_json(input.as_bytes(), ImportIds::Preserve)
+            .unwrap();
+
+        assert!(report.imported.is_empty());
+        assert!(matches!(
+            report.failures[0].error,
+            TinyBaseError::Exists { .. }
+        ));
+        assert!(matches!(
+            report.failures[1].error,
+            TinyBaseError::IdOutOfRange(id) if id == u64::MAX
+        ));
+        assert!(copy.insert("value6".to_string()).unwrap() < ahead);
+
+        // IDs far ahead are reserved at once.
+        let far = 1u64 << 40;
+        let input = format!("{{\"id\":{},\"data\":\"far\"}}\n", far);
+        let report = copy
+            .import_json(input.as_bytes(), ImportIds::Preserve)
+            .unwrap();
+        assert_eq!(report.imported, vec![far]);
+        assert_eq!(copy.insert("value7".to_string()).unwrap(), far + 1);
+    }
+
+    #[cfg(feature = "csv")]
+    #[test]
+    fn csv_export_import() {
+        #[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+        struct Person {
+            name: String,
+            age: u32,
+        }
+
+        let db = TinyBase::new(None, true);
+        let table: Table<Person> = db.open_table("test_table").unwrap();
+        let id = table
+            .insert(Person {
+                name: "Smith, John".to_string(),
+                age: 30,
+            })
+            .unwrap();
+
+        let mut exported = vec![];
+        assert_eq!(table.export_csv(&mut exported).unwrap(), 1);
+        assert_eq!(
+            String::from_utf8(exported.clone()).unwrap(),
+            format!("id,name,age\n{},\"Smith, John\",30\n", id)
+        );
+
+        let copy: Table<Person> = db.open_table("copy").unwrap();
+        let name = copy
+            .create_index("name", |person| person.name.to_owned())
+            .unwrap();
+        copy.constraint(Constraint::unique(&name)).unwrap();
+
+        let report = copy
+            .import_csv(exported.as_slice(), ImportIds::Preserve)
+            .unwrap();
+        assert_eq!(report.imported, vec![id]);
+
+        // Columns are matched by name, so their order doesn't matter.
+        let input = "id,age,name\n1,40,Jane\n2,forty,Joe\n3,50,\"Smith, John\"\n";
+        let report = copy
+            .import_csv(input.as_bytes(), ImportIds::Regenerate)
+            .unwrap();
+
+        assert_eq!(report.imported.len(), 1);
+        assert_eq!(
+            copy.select(report.imported[0]).unwrap().unwrap().data.age,
+            40
+        );
+        assert_eq!(report.failures.len(), 2);
+        assert_eq!(report.failures[0].line, 3);
+        assert!(matches!(report.failures[0].error, TinyBaseError::Csv(_)));
+        assert_eq!(report.failures[1].line, 4);
+        assert!(matches!(
+            report.failures[1].error,
+            TinyBaseError::Exists { .. }
+        ));
+
+        assert!(matches!(
+            copy.import_csv("name,age\n".as_bytes(), ImportIds::Regenerate),
+            Err(TinyBaseError::CsvHeader(_))
+        ));
+
+        // Malformed rows are reported like any other failure.
+        let input = "id,name,age\n1,Jane\n2,Joe,40\n";
+        let report = copy
+            .import_csv(input.as_bytes(), ImportIds::Regenerate)
+            .unwrap();
+        assert_eq!(report.imported.len(), 1);
+        assert_eq!(report.failures[0].line, 2);
+        assert!(matches!(report.failures[0].error, TinyBaseError::Csv(_)));
+
+        // Empty tables still have a header, so they can be imported.
+        let empty: Table<Person> = db.open_table("empty").unwrap();
+        let mut exported = vec![];
+        assert_eq!(empty.export_csv(&mut exported).unwrap(), 0);
+        assert_eq!(
+            String::from_utf8(exported.clone()).unwrap(),
+            "id,name,age\n"
+        );
+
+        let report = copy
+            .import_csv(exported.as_slice(), ImportIds::Preserve)
+            .unwrap();
+        assert!(report.imported.is_empty() && report.failures.is_empty());
+    }
+}
@@ -1,22 +1,31 @@
 use std::any::Any;
-use std::ops::Deref;
+use std::collections::BTreeSet;
+use std::ops::{Bound, Deref, RangeBounds};
 use std::sync::{Arc, Weak};
 use std::vec;
 
 use serde::de::DeserializeOwned;
 use serde::Serialize;
-use sled::{Db, Tree};
+use sled::transaction::{
+    ConflictableTransactionError, ConflictableTransactionResult, Transactional, TransactionalTree,
+};
+use sled::{Db, IVec, Tree};
 
-use crate::encoding::{decode, encode};
+use crate::batch::transaction_error;
+use crate::encoding::{decode, decode_key, encode, encode_key};
 use crate::record::Record;
-use crate::result::DbResult;
-use crate::subscriber::{self, Subscriber};
-use crate::table::{TableInner, TableType};
+use crate::result::{DbResult, TinyBaseError};
+use crate::schema::Schema;
+use crate::table::{TableInner, TableType, META_TREE};
 
 use self::private::AnyIndexInternal;
 
-pub trait IndexType: Serialize + DeserializeOwned {}
-impl<T: Serialize + DeserializeOwned> IndexType for T {}
+/// Number of records written per transaction while rebuilding an index.
+const REBUILD_BATCH_SIZE: usize = 1_000;
+
+/// Type of the keys of an index, which must be `Send + Sync` for the same reason as [`TableType`].
+pub trait IndexType: Serialize + DeserializeOwned + Send + Sync {}
+impl<T: Serialize + DeserializeOwned + Send + Sync> IndexType for T {}
 
 /// Provides methods for interacting with an index on a typed table.
 pub struct Index<T: TableType + 'static, I: IndexType>(pub(crate) Arc<IndexInner<T, I>>);
@@ -42,23 +51,34 @@ pub struct IndexInner<T: TableType + 'static, I: IndexType> {
     key_func: Box<dyn Fn(&T) -> I + Send + Sync>,
     /// Built index, each key can have multiple matching records.
     indexed_data: Tree,
-    /// Reference to uncommitted operation log.
-    subscriber: Subscriber<T>,
+    /// Key each record is currently stored under, so changes can be applied by ID alone.
+    indexed_ids: Tree,
+    /// Tree where the fingerprint and progress of the index are persisted.
+    meta: Tree,
+    /// Decodes records written through any handle of the table.
+    schema: Arc<Schema<T>>,
+    /// Identifies the version of the key function the index was built with, as given by the user
+    /// so it stays the same across builds.
+    fingerprint: String,
 }
 
-impl<T: TableType, I: IndexType> IndexInner<T, I> {
-    /// Creates a new index with the given name, engine, table data, key function, and subscriber.
+impl<T: TableType, I: IndexType + 'static> IndexInner<T, I> {
+    /// Creates a new index with the given name, engine, table data and key function.
     ///
     /// This method is intended for internal use and should not be called directly. Instead, use the
     /// [`crate::Table`]'s `create_index()` method.
     ///
+    /// If the index was persisted by an earlier run with the same fingerprint, only the writes
+    /// made to the table since then are applied. Otherwise the index is rebuilt from scratch.
+    /// Once created, the index is updated within the same transaction as every table write.
+    ///
     /// # Arguments
     ///
     /// * `idx_name` - The name of the index.
     /// * `engine` - The database engine.
     /// * `table` - A weak pointer to the table.
+    /// * `version` - The version of the key function.
     /// * `key_func` - A function which computes the index key for each record.
-    /// * `subscriber` - A subscriber to uncommitted operation log.
     ///
     /// # Returns
     ///
@@ -67,106 +87,134 @@ impl<T: TableType, I: IndexType> IndexInner<T, I> {
         idx_name: &str,
         engine: &Db,
         table: Weak<TableInner<T>>,
+        version: u32,
         key_func: impl Fn(&T) -> I + Send + Sync + 'static,
-        subscriber: Subscriber<T>,
-    ) -> DbResult<Self> {
-        let new_index = Self {
-            table,
+    ) -> DbResult<Arc<Self>> {
+        let table = table.upgrade().unwrap();
+        let new_index = Arc::new(Self {
+            table: Arc::downgrade(&table),
             key_func: Box::new(key_func),
             indexed_data: engine.open_tree(idx_name)?,
-            subscriber,
-        };
+            indexed_ids: engine.open_tree(format!("{}#ids", idx_name))?,
+            meta: engine.open_tree(META_TREE)?,
+            schema: table.schema.clone(),
+            fingerprint: format!("v{}", version),
+        });
+
+        let root = table.locked();
+        let _commit = table.commits.read().unwrap();
 
-        new_index.sync()?;
+        new_index.open(&table, &root)?;
+        // Attach while still holding the lock so no write can slip in between.
+        table.attach_index(new_index.index_name(), Arc::downgrade(&new_index) as _);
 
         Ok(new_index)
     }
 
-    /// Resync index to be up to date with table.
-    pub fn sync(&self) -> DbResult<()> {
-        self.indexed_data.clear()?;
+    /// Bring the persisted index up to date, rebuilding it only if it can't be caught up.
+    fn open(&self, table: &TableInner<T>, root: &Tree) -> DbResult<()> {
+        if let Some(bytes) = self.meta.get(self.meta_key())? {
+            let (fingerprint, seq): (String, u64) = decode(&bytes)?;
 
-        let table = self.table.upgrade().unwrap();
-        let root = table.root.write().unwrap();
-        for key in root.iter().keys() {
-            // This should always succeed
-            if let Some(data) = root.get(&key.clone()?)? {
-                self.insert(&Record {
-                    id: decode(&key?)?,
-                    data: decode(&data)?,
-                })?;
+            if fingerprint == self.fingerprint && seq == table.seq()? {
+                return Ok(());
             }
-        }
 
-        Ok(())
-    }
+            if fingerprint == self.fingerprint && seq < table.seq()? {
+                table.check_writable()?;
+
+                if let Some(changes) = table.changes_since(seq)? {
+                    let ids: BTreeSet<u64> = changes.into_iter().collect();
+
+                    let mut records = vec![];
+                    for id in ids {
+                        records.push((id, table.tree_select(root, id)?.map(|record| record.data)));
+                    }
 
-    /// Commits the received events from the main table to the index.
-    fn commit_log(&self) -> DbResult<()> {
-        // Commit log of events on the main table.
-        while let Ok(event) = self.subscriber.rx.try_recv() {
-            match event {
-                subscriber::Event::Remove(record) => self.remove(&record)?,
-                subscriber::Event::Insert(record) => self.insert(&record)?,
-                subscriber::Event::Update {
-                    id,
-                    old_data,
-                    new_data,
-                } => {
-                    self.remove(&Record { id, data: old_data })?;
-                    self.insert(&Record { id, data: new_data })?;
+                    let writes: Vec<(u64, Option<&T>)> = records
+                        .iter()
+                        .map(|(id, data)| (*id, data.as_ref()))
+                        .collect();
+
+                    return self
+                        .changes(&writes, Some(table.seq()?))?
+                        .commit(&self.meta);
                 }
             }
         }
 
-        Ok(())
+        self.rebuild(table, root)
     }
 
-    /// Insert a record into the index. The index key will be computed.
-    ///
-    /// # Arguments
-    ///
-    /// * `record` - The record to insert.
-    fn insert(&self, record: &Record<T>) -> DbResult<()> {
-        let key = encode(&(self.key_func)(&record.data))?;
+    /// Resync index to be up to date with table.
+    pub fn sync(&self) -> DbResult<()> {
+        let table = self.table.upgrade().unwrap();
+        let root = table.locked();
+        let _commit = table.commits.read().unwrap();
 
-        if let Some(data) = self.indexed_data.get(&key)? {
-            let mut vec: Vec<u64> = decode(&data)?;
-            vec.push(record.id);
-            self.indexed_data.insert(key, encode(&vec)?)?;
-        } else {
-            self.indexed_data.insert(key, encode(&vec![record.id])?)?;
+        self.rebuild(&table, &root)
+    }
+
+    /// Rebuild the index from every record in the locked root tree.
+    fn rebuild(&self, table: &TableInner<T>, root: &Tree) -> DbResult<()> {
+        table.check_writable()?;
+
+        // Forget the persisted state first, so a crash part way through rebuilds again.
+        self.meta.remove(self.meta_key())?;
+        self.indexed_data.clear()?;
+        self.indexed_ids.clear()?;
+
+        let mut records = vec![];
+        for entry in root.iter() {
+            let (key, data) = entry?;
+            records.push((decode::<u64>(&key)?, table.schema.decode(&data)?));
+
+            if records.len() == REBUILD_BATCH_SIZE {
+                self.rebuild_batch(&records)?;
+                records.clear();
+            }
         }
 
-        Ok(())
+        self.rebuild_batch(&records)?;
+        self.changes(&[], Some(table.seq()?))?.commit(&self.meta)
+    }
+
+    /// Index a batch of records while rebuilding.
+    fn rebuild_batch(&self, records: &[(u64, T)]) -> DbResult<()> {
+        let writes: Vec<(u64, Option<&T>)> =
+            records.iter().map(|(id, data)| (*id, Some(data))).collect();
+
+        self.changes(&writes, None)?.commit(&self.meta)
     }
 
-    /// Delete a record from the index.
-    /// The record will compute an index key to delete by.
+    /// Key under which the state of the index is persisted.
+    fn meta_key(&self) -> String {
+        format!("{}#index", self.index_name())
+    }
+
+    /// Compute the changes to the index caused by writes to the table.
     ///
     /// # Arguments
     ///
-    /// * `record` - The record to delete.
-    fn remove(&self, record: &Record<T>) -> DbResult<()> {
-        let key = encode(&(self.key_func)(&record.data))?;
-
-        if let Some(data) = self.indexed_data.get(&key)? {
-            let mut index_values: Vec<u64> = decode(&data)?;
-
-            // We can remove the entire node here since its one element.
-            if index_values.len() < 2 {
-                self.indexed_data.remove(&key)?;
-            } else {
-                // Remove the single ID from here.
-                if let Some(pos) = index_values.iter().position(|id| *id == record.id) {
-                    index_values.remove(pos);
-                    // Replace the row with one that doesn't have the element.
-                    self.indexed_data.insert(&key, encode(&index_values)?)?;
-                }
-            }
+    /// * `writes` - The new data of each record, [`None`] if the record was removed.
+    /// * `seq` - The change log sequence number to persist the index as caught up to.
+    fn changes(&self, writes: &[(u64, Option<&T>)], seq: Option<u64>) -> DbResult<IndexChanges> {
+        let mut changes = vec![];
+        for (id, data) in writes {
+            changes.push((*id, data.map(|data| self.generate_key(data)).transpose()?));
         }
 
-        Ok(())
+        let meta = match seq {
+            Some(seq) => Some((self.meta_key(), encode(&(&self.fingerprint, seq))?)),
+            None => None,
+        };
+
+        Ok(IndexChanges {
+            data: self.indexed_data.clone(),
+            ids: self.indexed_ids.clone(),
+            meta,
+            changes,
+        })
     }
 
     /// Delete records from the table and the index based on the given query.
@@ -200,12 +248,10 @@ impl<T: TableType, I: IndexType> IndexInner<T, I> {
     ///
     /// All selected [`Record`] instances.
     pub fn select(&self, query: &I) -> DbResult<Vec<Record<T>>> {
-        self.commit_log()?;
-
         let table = self.table.upgrade().unwrap();
 
         Ok(
-            if let Ok(Some(bytes)) = self.indexed_data.get(encode(&query)?) {
+            if let Ok(Some(bytes)) = self.indexed_data.get(encode_key(&query)?) {
                 let ids: Vec<u64> = decode(&bytes)?;
 
                 let mut results = vec![];
@@ -222,14 +268,129 @@ impl<T: TableType, I: IndexType> IndexInner<T, I> {
         )
     }
 
+    /// Select the IDs of records stored under the given query key.
+    pub(crate) fn select_ids(&self, query: &I) -> DbResult<Vec<u64>> {
+        match self.indexed_data.get(encode_key(query)?)? {
+            Some(bytes) => decode(&bytes),
+            None => Ok(vec![]),
+        }
+    }
+
+    /// Select records from the table whose index key falls within the given range.
+    ///
+    /// # Arguments
+    ///
+    /// * `range` - The range of index keys to select.
+    ///
+    /// # Returns
+    ///
+    /// All selected [`Record`] instances, ordered by their index key.
+    pub fn range<R: RangeBounds<I>>(&self, range: R) -> DbResult<Vec<Record<T>>> {
+        let table = self.table.upgrade().unwrap();
+
+        let mut results = vec![];
+        for id in self.range_ids(range)? {
+            if let Some(record) = table.select(id?)? {
+                results.push(record);
+            }
+        }
+
+        Ok(results)
+    }
+
+    /// Lazily iterate over the IDs of records whose index key falls within the given range,
+    /// ordered by their index key.
+    pub(crate) fn range_ids<R: RangeBounds<I>>(
+        &self,
+        range: R,
+    ) -> DbResult<impl Iterator<Item = DbResult<u64>>> {
+        let start = encode_bound(range.start_bound())?;
+        let end = encode_bound(range.end_bound())?;
+
+        Ok(self
+            .indexed_data
+            .range::<Vec<u8>, _>((start, end))
+            .flat_map(|entry| {
+                match entry
+                    .map_err(TinyBaseError::from)
+                    .and_then(|(_, bytes)| decode::<Vec<u64>>(&bytes))
+                {
+                    Ok(ids) => ids.into_iter().map(Ok).collect(),
+                    Err(err) => vec![Err(err)],
+                }
+            }))
+    }
+
+    /// Select records from the table whose index key is between `start` and `end` (inclusive).
+    ///
+    /// # Arguments
+    ///
+    /// * `start` - The lowest index key to select.
+    /// * `end` - The highest index key to select.
+    ///
+    /// # Returns
+    ///
+    /// All selected [`Record`] instances, ordered by their index key.
+    pub fn between(&self, start: &I, end: &I) -> DbResult<Vec<Record<T>>> {
+        self.range((Bound::Included(start), Bound::Included(end)))
+    }
+
+    /// Select records from the table whose index key is strictly greater than `query`.
+    ///
+    /// # Arguments
+    ///
+    /// * `query` - A reference to the lower bound key.
+    ///
+    /// # Returns
+    ///
+    /// All selected [`Record`] instances, ordered by their index key.
+    pub fn greater_than(&self, query: &I) -> DbResult<Vec<Record<T>>> {
+        self.range((Bound::Excluded(query), Bound::Unbounded))
+    }
+
+    /// Select records from the table whose index key is strictly less than `query`.
+    ///
+    /// # Arguments
+    ///
+    /// * `query` - A reference to the upper bound key.
+    ///
+    /// # Returns
+    ///
+    /// All selected [`Record`] instances, ordered by their index key.
+    pub fn less_than(&self, query: &I) -> DbResult<Vec<Record<T>>> {
+        self.range((Bound::Unbounded, Bound::Excluded(query)))
+    }
+
+    /// Get the smallest index key currently present in the index.
+    ///
+    /// # Returns
+    ///
+    /// An [`Option`] containing the smallest key, or [`None`] if the index is empty.
+    pub fn min(&self) -> DbResult<Option<I>> {
+        match self.indexed_data.first()? {
+            Some((key, _)) => Ok(Some(decode_key(&key)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Get the largest index key currently present in the index.
+    ///
+    /// # Returns
+    ///
+    /// An [`Option`] containing the largest key, or [`None`] if the index is empty.
+    pub fn max(&self) -> DbResult<Option<I>> {
+        match self.indexed_data.last()? {
+            Some((key, _)) => Ok(Some(decode_key(&key)?)),
+            None => Ok(None),
+        }
+    }
+
     /// Static select that doesn't obtain a read lock.
     fn tree_select(&self, tree: &Tree, query: &I) -> DbResult<Vec<Record<T>>> {
-        self.commit_log()?;
-
         let table = self.table.upgrade().unwrap();
 
         Ok(
-            if let Ok(Some(bytes)) = self.indexed_data.get(encode(&query)?) {
+            if let Ok(Some(bytes)) = self.indexed_data.get(encode_key(&query)?) {
                 let ids: Vec<u64> = decode(&bytes)?;
 
                 let mut results = vec![];
@@ -257,11 +418,9 @@ impl<T: TableType, I: IndexType> IndexInner<T, I> {
     ///
     /// All updated [`Record`] instances.
     pub fn update(&self, query: &I, updater: fn(T) -> T) -> DbResult<Vec<Record<T>>> {
-        self.commit_log()?;
-
         let table = self.table.upgrade().unwrap();
 
-        if let Ok(Some(bytes)) = self.indexed_data.get(encode(&query)?) {
+        if let Ok(Some(bytes)) = self.indexed_data.get(encode_key(&query)?) {
             let ids: Vec<u64> = decode(&bytes)?;
             table.update(&ids, updater)
         } else {
@@ -276,16 +435,147 @@ impl<T: TableType, I: IndexType> IndexInner<T, I> {
     }
 
     pub fn generate_key(&self, data: &T) -> DbResult<Vec<u8>> {
-        encode(&(self.key_func)(&data))
+        encode_key(&(self.key_func)(data))
     }
 }
 
+/// Index which is maintained within the same transaction as the writes to its table.
+///
+/// Writes are passed encoded, so an index is written through every handle of its table.
+pub(crate) trait IndexWriter: Send + Sync {
+    /// Compute the changes to the index caused by writes to the table.
+    ///
+    /// # Arguments
+    ///
+    /// * `writes` - The encoded data of each record, [`None`] if the record was removed.
+    /// * `seq` - The change log sequence number of the last write.
+    fn stage(&self, writes: &[(u64, Option<&[u8]>)], seq: u64) -> DbResult<IndexChanges>;
+}
+
+impl<T: TableType, I: IndexType + 'static> IndexWriter for IndexInner<T, I> {
+    fn stage(&self, writes: &[(u64, Option<&[u8]>)], seq: u64) -> DbResult<IndexChanges> {
+        let mut records = vec![];
+        for (id, data) in writes {
+            records.push((*id, data.map(|data| self.schema.decode(data)).transpose()?));
+        }
+
+        let writes: Vec<(u64, Option<&T>)> = records
+            .iter()
+            .map(|(id, data)| (*id, data.as_ref()))
+            .collect();
+
+        self.changes(&writes, Some(seq))
+    }
+}
+
+/// Changes to an index which are ready to be applied within a transaction.
+pub(crate) struct IndexChanges {
+    pub(crate) data: Tree,
+    pub(crate) ids: Tree,
+    /// Persisted state of the index once the changes are applied.
+    meta: Option<(String, Vec<u8>)>,
+    /// ID of each written record and its new encoded key, [`None`] if the record was removed.
+    changes: Vec<(u64, Option<Vec<u8>>)>,
+}
+
+impl IndexChanges {
+    /// Apply the changes in a transaction of their own.
+    fn commit(&self, meta: &Tree) -> DbResult<()> {
+        (&self.data, &self.ids, meta)
+            .transaction(|(data, ids, meta)| self.apply(data, ids, meta))
+            .map_err(transaction_error)
+    }
+
+    /// Apply the changes within a transaction over the index trees and the metadata tree.
+    pub(crate) fn apply(
+        &self,
+        data: &TransactionalTree,
+        ids: &TransactionalTree,
+        meta: &TransactionalTree,
+    ) -> ConflictableTransactionResult<(), TinyBaseError> {
+        for (id, key) in &self.changes {
+            let id_key = encode(id).map_err(ConflictableTransactionError::Abort)?;
+
+            // Move the record out of the key it is currently stored under.
+            if let Some(old_key) = ids.get(&id_key)? {
+                let mut index_values = decode_ids(data.get(&old_key)?)?;
+
+                if let Some(pos) = index_values.iter().position(|other| other == id) {
+                    index_values.remove(pos);
+
+                    // We can remove the entire node once no records are left.
+                    if index_values.is_empty() {
+                        data.remove(&old_key)?;
+                    } else {
+                        data.insert(&old_key, encode_ids(&index_values)?)?;
+                    }
+                }
+            }
+
+            match key {
+                Some(key) => {
+                    let mut index_values = decode_ids(data.get(key.as_slice())?)?;
+                    if !index_values.contains(id) {
+                        index_values.push(*id);
+                        data.insert(key.as_slice(), encode_ids(&index_values)?)?;
+                    }
+
+                    ids.insert(id_key, key.as_slice())?;
+                }
+                None => {
+                    ids.remove(id_key)?;
+                }
+            }
+        }
+
+        if let Some((key, value)) = &self.meta {
+            meta.insert(key.as_str(), value.as_slice())?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Decode the IDs stored under an index key within a transaction.
+fn decode_ids(bytes: Option<IVec>) -> ConflictableTransactionResult<Vec<u64>, TinyBaseError> {
+    match bytes {
+        Some(bytes) => decode(&bytes).map_err(ConflictableTransactionError::Abort),
+        None => Ok(vec![]),
+    }
+}
+
+/// Encode the IDs stored under an index key within a transaction.
+fn encode_ids(ids: &[u64]) -> ConflictableTransactionResult<Vec<u8>, TinyBaseError> {
+    encode(&ids).map_err(ConflictableTransactionError::Abort)
+}
+
+/// Encode a bound on the index key into a bound on the raw index tree key.
+fn encode_bound<I: IndexType>(bound: Bound<&I>) -> DbResult<Bound<Vec<u8>>> {
+    Ok(match bound {
+        Bound::Included(key) => Bound::Included(encode_key(key)?),
+        Bound::Excluded(key) => Bound::Excluded(encode_key(key)?),
+        Bound::Unbounded => Bound::Unbounded,
+    })
+}
+
 pub(crate) mod private {
     use super::*;
 
+    /// Raw index entries, each being an encoded key and the IDs stored under it.
+    pub type IndexEntries = Box<dyn Iterator<Item = DbResult<(IVec, Vec<u64>)>>>;
+
     /// Additional methods for index which are only for internal use.
     pub trait AnyIndexInternal<T: TableType> {
         fn tree_exists(&self, tree: &Tree, record: &Record<T>) -> DbResult<Vec<u64>>;
+        /// Select the IDs of records matching the query value, in ascending order.
+        fn search_ids(&self, value: &dyn Any) -> DbResult<Vec<u64>>;
+        /// Number of records stored under the query value.
+        fn estimate(&self, value: &dyn Any) -> DbResult<usize>;
+        /// Check if the key computed from `data` is equal to the query value.
+        fn key_matches(&self, value: &dyn Any, data: &T) -> DbResult<bool>;
+        /// Walk the index entries in key order, starting from (and including) `start`.
+        /// The walk is descending when `reverse` is set.
+        fn entries(&self, start: Bound<Vec<u8>>, reverse: bool) -> DbResult<IndexEntries>;
     }
 }
 
@@ -303,10 +593,51 @@ where
             .map(|record: &Record<T>| record.id)
             .collect())
     }
+
+    fn search_ids(&self, value: &dyn Any) -> DbResult<Vec<u64>> {
+        let mut ids = self.select_ids(value.downcast_ref::<I>().unwrap())?;
+        ids.sort_unstable();
+        Ok(ids)
+    }
+
+    fn estimate(&self, value: &dyn Any) -> DbResult<usize> {
+        Ok(self.select_ids(value.downcast_ref::<I>().unwrap())?.len())
+    }
+
+    fn key_matches(&self, value: &dyn Any, data: &T) -> DbResult<bool> {
+        let i = value.downcast_ref::<I>().unwrap();
+        Ok(encode_key(i)? == self.generate_key(data)?)
+    }
+
+    fn entries(&self, start: Bound<Vec<u8>>, reverse: bool) -> DbResult<private::IndexEntries> {
+        let decode_entry = |entry: sled::Result<(IVec, IVec)>| {
+            let (key, bytes) = entry?;
+            let mut ids: Vec<u64> = decode(&bytes)?;
+            ids.sort_unstable();
+            Ok((key, ids))
+        };
+
+        Ok(if reverse {
+            Box::new(
+                self.indexed_data
+                    .range((Bound::Unbounded, start))
+                    .rev()
+                    .map(decode_entry),
+            )
+        } else {
+            Box::new(
+                self.indexed_data
+                    .range((start, Bound::Unbounded))
+                    .map(decode_entry),
+            )
+        })
+    }
 }
 
 /// Type which [`Index`] can be casted to which doesn't require the `I` type parameter.
-pub trait AnyIndex<T: TableType>: private::AnyIndexInternal<T> {
+///
+/// Indexes are `Send + Sync`, see [`TableType`].
+pub trait AnyIndex<T: TableType>: private::AnyIndexInternal<T> + Send + Sync {
     /// Check if a record exists by the index key.
     ///
     /// # Arguments
@@ -336,7 +667,10 @@ where
     }
 
     fn exists(&self, record: &Record<T>) -> DbResult<Vec<u64>> {
-        self.tree_exists(&self.table.upgrade().unwrap().root.read().unwrap(), record)
+        self.tree_exists(
+            &self.table.upgrade().unwrap().shared.root.read().unwrap(),
+            record,
+        )
     }
 
     fn gen_key(&self, data: &T) -> DbResult<Vec<u8>> {
@@ -397,6 +731,67 @@ mod tests {
         assert_eq!(record_2.len(), 0);
     }
 
+    #[test]
+    fn index_range() {
+        let db = TinyBase::new(None, true);
+        let table: Table<String> = db.open_table("test_table").unwrap();
+
+        // Create an index for the table
+        let index = table.create_index("length", |value| value.len()).unwrap();
+
+        // Insert string values of different lengths into the table
+        table.insert("aaaa".to_string()).unwrap();
+        table.insert("a".to_string()).unwrap();
+        table.insert("aaa".to_string()).unwrap();
+        table.insert("aa".to_string()).unwrap();
+
+        let lengths = |records: Vec<Record<String>>| -> Vec<usize> {
+            records.iter().map(|record| record.data.len()).collect()
+        };
+
+        assert_eq!(lengths(index.range(2..4).unwrap()), vec![2, 3]);
+        assert_eq!(lengths(index.range(..).unwrap()), vec![1, 2, 3, 4]);
+        assert_eq!(lengths(index.between(&2, &3).unwrap()), vec![2, 3]);
+        assert_eq!(lengths(index.greater_than(&2).unwrap()), vec![3, 4]);
+        assert_eq!(lengths(index.less_than(&2).unwrap()), vec![1]);
+        assert!(index.range(5..).unwrap().is_empty());
+    }
+
+    #[test]
+    fn index_range_ordered_keys() {
+        let db = TinyBase::new(None, true);
+        let table: Table<(String, i32)> = db.open_table("test_table").unwrap();
+
+        let name = table
+            .create_index("name", |(name, _)| name.to_owned())
+            .unwrap();
+        let score = table.create_index("score", |(_, score)| *score).unwrap();
+
+        for (value, points) in [("bb", 5), ("a", -20), ("ab", 0), ("b", -3)] {
+            table.insert((value.to_string(), points)).unwrap();
+        }
+
+        let names: Vec<String> = name
+            .range(..)
+            .unwrap()
+            .into_iter()
+            .map(|record| record.data.0)
+            .collect();
+        assert_eq!(names, vec!["a", "ab", "b", "bb"]);
+
+        let scores: Vec<i32> = score
+            .less_than(&1)
+            .unwrap()
+            .into_iter()
+            .map(|record| record.data.1)
+            .collect();
+        assert_eq!(scores, vec![-20, -3, 0]);
+
+        assert_eq!(score.min().unwrap(), Some(-20));
+        assert_eq!(score.max().unwrap(), Some(5));
+        assert_eq!(name.max().unwrap(), Some("bb".to_string()));
+    }
+
     #[test]
     fn index_update() {
         let db = TinyBase::new(None, true);
@@ -423,6 +818,99 @@ mod tests {
         assert_eq!(updated_records[0].data, "updated_value");
     }
 
+    #[test]
+    fn index_persisted() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let db = TinyBase::new(None, true);
+
+        // Count key computations to tell a catch up apart from a rebuild.
+        let calls = Arc::new(AtomicUsize::new(0));
+        let open = |table: &Table<String>, version: u32| {
+            let calls = calls.clone();
+            table
+                .create_index_versioned("length", version, move |value: &String| {
+                    calls.fetch_add(1, Ordering::SeqCst);
+                    value.len()
+                })
+                .unwrap()
+        };
+
+        let (short, long) = {
+            let table: Table<String> = db.open_table("test_table").unwrap();
+            let index = open(&table, 0);
+
+            let short = table.insert("a".to_string()).unwrap();
+            let long = table.insert("aaa".to_string()).unwrap();
+            table.insert("bbb".to_string()).unwrap();
+            assert_eq!(index.select(&3).unwrap().len(), 2);
+
+            (short, long)
+        };
+
+        // The table is opened again, so the index only has what was persisted to go on.
+        let table: Table<String> = db.open_table("test_table").unwrap();
+
+        // Reopening an up to date index doesn't touch any record.
+        calls.store(0, Ordering::SeqCst);
+        let index = open(&table, 0);
+        assert_eq!(calls.load(Ordering::SeqCst), 0);
+        assert_eq!(index.select(&1).unwrap()[0].id, short);
+        drop(index);
+
+        // Writes made while the index is closed are caught up on the next open.
+        table.delete(long).unwrap();
+        table.update(&[short], |_| "aaaa".to_string()).unwrap();
+        calls.store(0, Ordering::SeqCst);
+        let index = open(&table, 0);
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert!(index.select(&1).unwrap().is_empty());
+        assert_eq!(index.select(&3).unwrap().len(), 1);
+        assert_eq!(index.select(&4).unwrap()[0].id, short);
+        drop(index);
+
+        // A new version of the key function rebuilds the index.
+        calls.store(0, Ordering::SeqCst);
+        let index = open(&table, 1);
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+        assert_eq!(index.range(..).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn index_written_with_table() {
+        let db = TinyBase::new(None, true);
+        let table: Table<String> = db.open_table("test_table").unwrap();
+        let index = table.create_index("length", |value| value.len()).unwrap();
+
+        // The index trees are written by the table write itself, not when the index is read.
+        let id = table.insert("aa".to_string()).unwrap();
+        assert_eq!(index.indexed_data.len(), 1);
+        assert_eq!(
+            index
+                .indexed_ids
+                .get(encode(&id).unwrap())
+                .unwrap()
+                .unwrap(),
+            encode_key(&2usize).unwrap()
+        );
+
+        table.update(&[id], |_| "aaa".to_string()).unwrap();
+        assert!(index
+            .indexed_data
+            .get(encode_key(&2usize).unwrap())
+            .unwrap()
+            .is_none());
+        assert!(index
+            .indexed_data
+            .get(encode_key(&3usize).unwrap())
+            .unwrap()
+            .is_some());
+
+        table.delete(id).unwrap();
+        assert!(index.indexed_data.is_empty());
+        assert!(index.indexed_ids.is_empty());
+    }
+
     #[test]
     fn index_exists() {
         let db = TinyBase::new(None, true);
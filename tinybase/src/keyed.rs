@@ -0,0 +1,293 @@
+use std::ops::RangeBounds;
+
+use serde::{Deserialize, Serialize};
+
+use crate::index::{Index, IndexType};
+use crate::result::DbResult;
+use crate::table::{Table, TableType};
+use crate::{Constraint, TinyBase};
+
+/// Name of the index holding the primary keys of a [`KeyedTable`].
+const KEY_INDEX: &str = "primary_key";
+
+/// Type of a caller supplied primary key, which records are ordered by.
+pub trait KeyType: IndexType + TableType + 'static {}
+impl<K: IndexType + TableType + 'static> KeyType for K {}
+
+/// A record of a [`KeyedTable`] along with its primary key.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Keyed<K, T> {
+    pub key: K,
+    pub data: T,
+}
+
+/// A table whose records are identified by a caller supplied primary key instead of a generated
+/// ID.
+///
+/// Records are stored in a [`Table`] of [`Keyed`] records ordered by a generated ID, with a unique
+/// index over the keys. Looking up a key reads the index and then the record, and iterating reads
+/// the records in the order of the index.
+pub struct KeyedTable<K: KeyType, T: TableType + 'static> {
+    table: Table<Keyed<K, T>>,
+    keys: Index<Keyed<K, T>, K>,
+}
+
+impl<K: KeyType, T: TableType + 'static> KeyedTable<K, T> {
+    /// Open a keyed table.
+    ///
+    /// # Arguments
+    ///
+    /// * `db` - The database containing the table.
+    /// * `name` - The name of the table.
+    pub(crate) fn open(db: &TinyBase, name: &str) -> DbResult<Self> {
+        let table: Table<Keyed<K, T>> = db.open_table(name)?;
+        let keys = table.create_index(KEY_INDEX, |record| record.key.clone())?;
+        table.constraint(Constraint::unique(&keys))?;
+
+        Ok(Self { table, keys })
+    }
+
+    /// Get the record with a key.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The primary key of the record.
+    ///
+    /// # Returns
+    ///
+    /// The data of the record, or [`None`] if there is no record with the key.
+    pub fn get(&self, key: &K) -> DbResult<Option<T>> {
+        Ok(self.keys.select(key)?.pop().map(|record| record.data.data))
+    }
+
+    /// Check whether a record with a key exists.
+    pub fn contains_key(&self, key: &K) -> DbResult<bool> {
+        Ok(!self.keys.select_ids(key)?.is_empty())
+    }
+
+    /// Insert a record with a key, replacing the record which already has that key.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The primary key of the record.
+    /// * `value` - The data of the record.
+    ///
+    /// # Returns
+    ///
+    /// The data of the replaced record, or [`None`] if there was no record with the key.
+    pub fn put(&self, key: K, value: T) -> DbResult<Option<T>> {
+        let record = Keyed { key, data: value };
+        let previous = self.table.replace(record, |record| {
+            Ok(self.keys.select_ids(&record.key)?.first().copied())
+        })?;
+
+        Ok(previous.map(|previous| previous.data.data))
+    }
+
+    /// Delete the record with a key.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The primary key of the record.
+    ///
+    /// # Returns
+    ///
+    /// The data of the deleted record, or [`None`] if there was no record with the key.
+    pub fn remove(&self, key: &K) -> DbResult<Option<T>> {
+        Ok(self.keys.delete(key)?.pop().map(|record| record.data.data))
+    }
+
+    /// Get the number of records in the table.
+    ///
+    /// This walks the entire table, so it is linear in the number of records.
+    pub fn len(&self) -> usize {
+        self.table.len()
+    }
+
+    /// Check if the table contains no records.
+    pub fn is_empty(&self) -> bool {
+        self.table.is_empty()
+    }
+
+    /// Create an index over the records of the table, which can be used in queries.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the index.
+    /// * `key_func` - A function which computes the index key for each record.
+    ///
+    /// # Returns
+    ///
+    /// An [`Index`] instance for the created index.
+    pub fn create_index<I: IndexType + 'static>(
+        &self,
+        name: &str,
+        key_func: impl Fn(&Keyed<K, T>) -> I + Send + Sync + 'static,
+    ) -> DbResult<Index<Keyed<K, T>, I>> {
+        self.table.create_index(name, key_func)
+    }
+
+    /// Lazily iterate over every record, ordered by key.
+    pub fn iter(&self) -> DbResult<impl Iterator<Item = DbResult<Keyed<K, T>>>> {
+        self.range(..)
+    }
+
+    /// Lazily iterate over the records whose key falls within a range, ordered by key.
+    ///
+    /// # Arguments
+    ///
+    /// * `range` - The range of keys to iterate over.
+    pub fn range<R: RangeBounds<K>>(
+        &self,
+        range: R,
+    ) -> DbResult<impl Iterator<Item = DbResult<Keyed<K, T>>>> {
+        let table = self.table.clone();
+
+        Ok(self.keys.range_ids(range)?.filter_map(move |id| {
+            // Records deleted since the key was read are skipped.
+            id.and_then(|id| table.select(id))
+                .map(|record| record.map(|record| record.data))
+                .transpose()
+        }))
+    }
+}
+
+impl<K: KeyType, T: TableType + 'static> Clone for KeyedTable<K, T> {
+    fn clone(&self) -> Self {
+        Self {
+            table: self.table.clone(),
+            keys: self.keys.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+    struct Person {
+        name: String,
+        age: u32,
+    }
+
+    fn person(name: &str, age: u32) -> Person {
+        Person {
+            name: name.to_string(),
+            age,
+        }
+    }
+
+    #[test]
+    fn keyed_table() {
+        let db = TinyBase::new(None, true);
+        let table: KeyedTable<String, Person> = db.open_keyed_table("people").unwrap();
+
+        assert_eq!(
+            table
+                .put("john@example.com".to_string(), person("John", 30))
+                .unwrap(),
+            None
+        );
+        table
+            .put("jane@example.com".to_string(), person("Jane", 25))
+            .unwrap();
+        table
+            .put("bob@example.com".to_string(), person("Bob", 40))
+            .unwrap();
+
+        assert_eq!(
+            table.get(&"john@example.com".to_string()).unwrap(),
+            Some(person("John", 30))
+        );
+        assert_eq!(table.get(&"joe@example.com".to_string()).unwrap(), None);
+
+        // Putting an existing key replaces its record.
+        assert_eq!(
+            table
+                .put("john@example.com".to_string(), person("John", 31))
+                .unwrap(),
+            Some(person("John", 30))
+        );
+        assert_eq!(table.len(), 3);
+
+        let keys: Vec<_> = table
+            .iter()
+            .unwrap()
+            .map(|record| record.unwrap().key)
+            .collect();
+        assert_eq!(
+            keys,
+            vec!["bob@example.com", "jane@example.com", "john@example.com"]
+        );
+
+        let range: Vec<_> = table
+            .range("c".to_string()..)
+            .unwrap()
+            .map(|record| record.unwrap().data.name)
+            .collect();
+        assert_eq!(range, vec!["Jane", "John"]);
+
+        assert_eq!(
+            table.remove(&"bob@example.com".to_string()).unwrap(),
+            Some(person("Bob", 40))
+        );
+        assert!(!table.contains_key(&"bob@example.com".to_string()).unwrap());
+
+        // Other indexes work over the data of the records.
+        let age = table.create_index("age", |record| record.data.age).unwrap();
+        assert_eq!(age.select(&31).unwrap()[0].data.key, "john@example.com");
+    }
+
+    #[test]
+    fn keyed_table_ordering() {
+        let db = TinyBase::new(None, true);
+        let table: KeyedTable<i64, String> = db.open_keyed_table("numbers").unwrap();
+
+        for key in [5, -3, 100, 0] {
+            table.put(key, key.to_string()).unwrap();
+        }
+
+        let keys: Vec<_> = table
+            .iter()
+            .unwrap()
+            .map(|record| record.unwrap().key)
+            .collect();
+        assert_eq!(keys, vec![-3, 0, 5, 100]);
+    }
+
+    #[test]
+    fn keyed_table_concurrent() {
+        let db = TinyBase::new(None, true);
+        let table: KeyedTable<u32, u32> = db.open_keyed_table("numbers").unwrap();
+
+        let threads: Vec<_> = (0..4)
+            .map(|thread| {
+                let table = table.clone();
+                std::thread::spawn(move || {
+                    for key in 0..20 {
+                        table.put(key, thread).unwrap();
+                        if key % 2 == 0 {
+                            table.remove(&key).unwrap();
+                        }
+                    }
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+
+        // Every key has at most one record, and keys which are never removed are kept.
+        assert!(table.len() <= 20);
+        for key in 0..20 {
+            assert_eq!(
+                table.contains_key(&key).unwrap(),
+                table.get(&key).unwrap().is_some()
+            );
+        }
+        for key in (1..20).step_by(2) {
+            assert!(table.contains_key(&key).unwrap());
+        }
+    }
+}
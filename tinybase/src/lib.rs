@@ -1,51 +1,107 @@
-use std::sync::Arc;
+use std::path::Path;
+use std::sync::{Arc, RwLock};
 
-use sled::Config;
+use durability::GroupCommit;
+
+pub mod builder;
+pub use builder::{Mode, TinyBaseBuilder};
 
 pub mod index;
 pub use index::Index;
 
 pub mod query_builder;
-pub use query_builder::{ConditionBuilder, QueryBuilder};
+pub use query_builder::{ConditionBuilder, Cursor, Order, Page, QueryBuilder};
+
+pub mod planner;
+pub use planner::Plan;
 
 pub mod result;
 pub use result::DbResult;
+use result::TinyBaseError;
 
 pub mod record;
 pub use record::Record;
 
 pub mod table;
-pub use table::Table;
-use table::{TableInner, TableType};
+use table::TableType;
+pub use table::{Table, TableIter};
+
+pub mod catalog;
+pub use catalog::TableInfo;
+
+pub mod codec;
+pub use codec::Codec;
+
+pub mod schema;
+pub use schema::TableBuilder;
+
+pub mod keyed;
+pub use keyed::{Keyed, KeyedTable};
+
+pub mod dynamic;
+pub use dynamic::{DynamicTable, JsonKey};
 
 pub mod constraint;
 pub use constraint::Constraint;
 
+pub mod transaction;
+pub use transaction::Transaction;
+
+pub mod export;
+pub use export::{ImportFailure, ImportIds, ImportReport};
+
+pub mod subscriber;
+pub use subscriber::{Event, Subscriber};
+
+#[cfg(feature = "async")]
+mod asynchronous;
+mod backup;
+mod batch;
+mod durability;
 mod encoding;
-mod subscriber;
 
 /// A tiny structured database based on sled.
 pub struct TinyBase {
     engine: sled::Db,
+    read_only: bool,
+    group_commit: Arc<GroupCommit>,
+    /// Held shared by every write to a tree, and exclusively while a backup is taken.
+    commits: Arc<RwLock<()>>,
+    open_tables: catalog::OpenTables,
 }
 
 impl TinyBase {
     /// Create a new instance of `TinyBase`.
     ///
+    /// See [`TinyBase::builder`] for more options, and to handle errors when opening.
+    ///
     /// # Arguments
     ///
-    /// * `path` - An optional path to the database file. If `None`, an in-memory database is created.
-    /// * `temporary` - If `true`, the database file will be deleted on close.
+    /// * `path` - An optional path to the database file. If `None`, a temporary database is created.
+    /// * `temporary` - If `true`, the database file will be deleted on close. Databases without a
+    ///   path are always temporary.
+    ///
+    /// # Panics
+    ///
+    /// If the database can't be opened.
     pub fn new(path: Option<&str>, temporary: bool) -> Self {
-        Self {
-            engine: if let Some(path) = path {
-                Config::new().path(path).temporary(temporary)
-            } else {
-                Config::new().temporary(temporary)
-            }
-            .open()
-            .unwrap(),
+        let builder = Self::builder().temporary(temporary);
+
+        match path {
+            Some(path) => builder.path(path),
+            None => builder,
         }
+        .open()
+        .unwrap()
+    }
+
+    /// Configure a new instance of `TinyBase`.
+    ///
+    /// # Returns
+    ///
+    /// A [`TinyBaseBuilder`] which opens the database.
+    pub fn builder() -> TinyBaseBuilder {
+        TinyBaseBuilder::new()
     }
 
     /// Open a table for a given type.
@@ -58,6 +114,197 @@ impl TinyBase {
     ///
     /// A `Table` instance for the given type.
     pub fn open_table<T: TableType>(&self, name: &str) -> DbResult<Table<T>> {
-        Ok(Table(Arc::new(TableInner::new(&self.engine, name)?)))
+        self.open_table_with_codec(name, codec::Bincode)
+    }
+
+    /// Open a table for a given type, with its records serialized by a [`Codec`].
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the table.
+    /// * `codec` - The codec of the records, which must be the same every time the table is opened.
+    ///
+    /// # Returns
+    ///
+    /// A `Table` instance for the given type, or [`TinyBaseError::CodecMismatch`] if the table
+    /// was created with another codec.
+    pub fn open_table_with_codec<T: TableType>(
+        &self,
+        name: &str,
+        codec: impl Codec,
+    ) -> DbResult<Table<T>> {
+        self.table_builder(name, codec).open()
+    }
+
+    /// Open a table whose records are identified by a caller supplied key, see [`KeyedTable`].
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the table.
+    ///
+    /// # Returns
+    ///
+    /// A [`KeyedTable`] instance for the given key and record types.
+    pub fn open_keyed_table<K: keyed::KeyType, T: TableType>(
+        &self,
+        name: &str,
+    ) -> DbResult<KeyedTable<K, T>> {
+        KeyedTable::open(self, name)
+    }
+
+    /// Open a table of JSON documents, see [`DynamicTable`].
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the table, which may have been created with any type.
+    ///
+    /// # Returns
+    ///
+    /// A [`DynamicTable`], or [`TinyBaseError::CodecMismatch`] if the table isn't stored as JSON.
+    pub fn open_dynamic_table(&self, name: &str) -> DbResult<DynamicTable> {
+        self.table_builder(name, codec::Json).dynamic().open()
+    }
+
+    /// Configure a table before opening it, such as its schema version and migrations.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the table.
+    /// * `codec` - The codec of the records, which must be the same every time the table is opened.
+    ///
+    /// # Returns
+    ///
+    /// A [`TableBuilder`] which opens the table.
+    pub fn table_builder<T: TableType, C: Codec>(
+        &self,
+        name: &str,
+        codec: C,
+    ) -> TableBuilder<'_, T, C> {
+        TableBuilder::new(self, name, codec)
+    }
+
+    /// Describe every table of the database, ordered by name.
+    ///
+    /// # Returns
+    ///
+    /// The [`TableInfo`] recorded in the catalog for each table, including its record type,
+    /// codec, indexes and constraints.
+    pub fn tables(&self) -> DbResult<Vec<TableInfo>> {
+        catalog::list(&self.engine)
+    }
+
+    /// Delete a table along with its indexes.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the table, which must not be open.
+    ///
+    /// # Returns
+    ///
+    /// Whether the table existed, or [`TinyBaseError::TableInUse`] if it is still open.
+    pub fn drop_table(&self, name: &str) -> DbResult<bool> {
+        if self.read_only {
+            return Err(TinyBaseError::ReadOnly);
+        }
+
+        let mut handles = self.open_tables.lock();
+        catalog::check_unused(&mut handles, name)?;
+
+        let _commits = self.commits.write().unwrap();
+        catalog::drop_table(&self.engine, name)
+    }
+
+    /// Rename a table along with its indexes.
+    ///
+    /// The records are copied to the new name, so this takes time proportional to the size of
+    /// the table.
+    ///
+    /// # Arguments
+    ///
+    /// * `from` - The name of the table, which must not be open.
+    /// * `to` - The new name of the table, which must not be taken.
+    pub fn rename_table(&self, from: &str, to: &str) -> DbResult<()> {
+        if self.read_only {
+            return Err(TinyBaseError::ReadOnly);
+        }
+
+        let mut handles = self.open_tables.lock();
+        catalog::check_unused(&mut handles, from)?;
+        catalog::check_unused(&mut handles, to)?;
+
+        let _commits = self.commits.write().unwrap();
+        catalog::rename_table(&self.engine, from, to)
+    }
+
+    /// Persist every write made so far to disk.
+    ///
+    /// # Returns
+    ///
+    /// The number of bytes flushed.
+    pub fn flush(&self) -> DbResult<usize> {
+        Ok(self.engine.flush()?)
+    }
+
+    /// Run a transaction across one or more tables.
+    ///
+    /// All writes made through the [`Transaction`] are committed atomically once the closure
+    /// returns `Ok`. If the closure returns an error or a constraint is violated, nothing is written.
+    ///
+    /// # Arguments
+    ///
+    /// * `f` - Closure which performs the writes of the transaction.
+    ///
+    /// # Returns
+    ///
+    /// The value returned by the closure.
+    pub fn transaction<R>(&self, f: impl FnOnce(&mut Transaction) -> DbResult<R>) -> DbResult<R> {
+        if self.read_only {
+            return Err(TinyBaseError::ReadOnly);
+        }
+
+        let mut transaction = Transaction::new(&self.engine, self.commits.clone());
+        let result = f(&mut transaction)?;
+        transaction.commit()?;
+
+        Ok(result)
+    }
+
+    /// Write a consistent snapshot of every table, index and piece of metadata to a file, while
+    /// the database stays available.
+    ///
+    /// Writes are paused until the snapshot has been written, reads are not.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path of the backup file, which is overwritten if it exists.
+    pub fn backup_to(&self, path: impl AsRef<Path>) -> DbResult<()> {
+        let _commits = self.commits.write().unwrap();
+        backup::backup(&self.engine, path.as_ref())
+    }
+
+    /// Restore a backup written by [`TinyBase::backup_to`] into this database, which must be
+    /// empty.
+    ///
+    /// The backup is verified before anything is restored, see [`TinyBase::verify_backup`].
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path of the backup file.
+    pub fn restore_from(&self, path: impl AsRef<Path>) -> DbResult<()> {
+        if self.read_only {
+            return Err(TinyBaseError::ReadOnly);
+        }
+
+        let _commits = self.commits.write().unwrap();
+        backup::restore(&self.engine, path.as_ref())
+    }
+
+    /// Check that a backup file is complete and hasn't been modified since it was written.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path of the backup file.
+    pub fn verify_backup(path: impl AsRef<Path>) -> DbResult<()> {
+        backup::verify(path.as_ref())
     }
 }
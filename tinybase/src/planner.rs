@@ -0,0 +1,532 @@
+//! Execution of query conditions and the plans describing how they were executed.
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fmt::{self, Display};
+
+use crate::{
+    query_builder::QueryCondition,
+    result::DbResult,
+    table::{Table, TableType},
+    Record,
+};
+
+/// Operation performed by a single step of a query [`Plan`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PlanOperation {
+    /// Records were looked up by key in the named index.
+    IndexLookup { index: String },
+    /// Every record in the table was visited.
+    Scan,
+    /// Records of the child were checked against a predicate.
+    Filter,
+    /// Records of the second child were removed from the records of the first child.
+    Difference,
+    /// Records present in both children were kept, the first child was evaluated first.
+    Intersect,
+    /// Records present in either child were kept.
+    Union,
+    /// The condition wasn't evaluated because a more selective condition matched nothing.
+    Skipped,
+}
+
+/// Description of how a query condition was executed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Plan {
+    /// Operation performed by this step.
+    pub operation: PlanOperation,
+    /// Number of records this step was estimated to produce before it was executed.
+    pub estimated_rows: usize,
+    /// Number of records this step actually produced, or [`None`] if it was skipped.
+    pub actual_rows: Option<usize>,
+    /// Steps whose results were used by this step.
+    pub children: Vec<Plan>,
+}
+
+impl Plan {
+    fn new(
+        operation: PlanOperation,
+        estimated_rows: usize,
+        actual_rows: usize,
+        children: Vec<Plan>,
+    ) -> Self {
+        Self {
+            operation,
+            estimated_rows,
+            actual_rows: Some(actual_rows),
+            children,
+        }
+    }
+
+    /// Check if any step of the plan had to fall back to a full table scan.
+    pub fn used_scan(&self) -> bool {
+        self.operation == PlanOperation::Scan || self.children.iter().any(Plan::used_scan)
+    }
+
+    /// Names of all indexes used by the plan.
+    pub fn indexes(&self) -> Vec<&str> {
+        let mut indexes = vec![];
+        if let PlanOperation::IndexLookup { index } = &self.operation {
+            indexes.push(index.as_str());
+        }
+
+        for child in &self.children {
+            indexes.extend(child.indexes());
+        }
+
+        indexes
+    }
+
+    fn fmt_indented(&self, f: &mut fmt::Formatter<'_>, depth: usize) -> fmt::Result {
+        write!(f, "{:indent$}", "", indent = depth * 2)?;
+
+        match &self.operation {
+            PlanOperation::IndexLookup { index } => write!(f, "IndexLookup {}", index)?,
+            operation => write!(f, "{:?}", operation)?,
+        }
+
+        match self.actual_rows {
+            Some(actual) => writeln!(f, " (estimated {}, actual {})", self.estimated_rows, actual)?,
+            None => writeln!(f, " (estimated {})", self.estimated_rows)?,
+        }
+
+        for child in &self.children {
+            child.fmt_indented(f, depth + 1)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Display for Plan {
+    /// Render the plan as an indented tree, one step per line.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_indented(f, 0)
+    }
+}
+
+/// Plans and executes query conditions on sorted sets of record IDs.
+///
+/// Record bodies are only fetched when a predicate needs them or once the final ID set is known.
+/// Fetched records are cached so they are never read twice during a single query.
+pub(crate) struct Planner<'a, T: TableType + 'static> {
+    table: &'a Table<T>,
+    /// Number of records in the table, computed lazily since it requires a walk over the table.
+    table_len: Option<usize>,
+    /// Records which have already been fetched by predicates.
+    fetched: HashMap<u64, Record<T>>,
+}
+
+impl<'a, T: TableType> Planner<'a, T> {
+    /// Creates a new planner for the given table.
+    ///
+    /// # Arguments
+    ///
+    /// * `table` - The table the conditions are executed on.
+    pub fn new(table: &'a Table<T>) -> Self {
+        Self {
+            table,
+            table_len: None,
+            fetched: HashMap::new(),
+        }
+    }
+
+    /// Fetch a record, preferring a copy already fetched during planning.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The ID of the record to fetch.
+    pub fn fetch(&mut self, id: u64) -> DbResult<Option<Record<T>>> {
+        match self.fetched.remove(&id) {
+            Some(record) => Ok(Some(record)),
+            None => self.table.select(id),
+        }
+    }
+
+    /// Number of records in the table.
+    fn table_len(&mut self) -> usize {
+        *self.table_len.get_or_insert_with(|| self.table.len())
+    }
+
+    /// Estimate the number of records matched by a condition.
+    ///
+    /// Indexed conditions are estimated by the number of IDs stored under their key. Conditions
+    /// which require a full table scan are estimated by the size of the table.
+    pub fn estimate(&mut self, condition: &QueryCondition<T>) -> DbResult<usize> {
+        Ok(match condition {
+            QueryCondition::By(index, value) => index.estimate(value.as_ref())?,
+            QueryCondition::And(left, right) => match (left.as_ref(), right.as_ref()) {
+                // Residual conditions are only evaluated against the other side.
+                (QueryCondition::Filter(_) | QueryCondition::Not(_), other)
+                | (other, QueryCondition::Filter(_) | QueryCondition::Not(_)) => {
+                    self.estimate(other)?
+                }
+                (left, right) => self.estimate(left)?.min(self.estimate(right)?),
+            },
+            QueryCondition::Or(left, right) => {
+                let estimate = self.estimate(left)?.saturating_add(self.estimate(right)?);
+                estimate.min(self.table_len())
+            }
+            QueryCondition::Not(_) | QueryCondition::Filter(_) => self.table_len(),
+        })
+    }
+
+    /// Recursively executes the condition.
+    ///
+    /// # Returns
+    ///
+    /// IDs of all matching records in ascending order.
+    pub fn select_ids(&mut self, condition: &QueryCondition<T>) -> DbResult<Vec<u64>> {
+        Ok(self.execute(condition)?.0)
+    }
+
+    /// Recursively executes the condition, recording how each step was executed.
+    ///
+    /// # Returns
+    ///
+    /// The [`Plan`] tree of the executed condition.
+    pub fn explain(&mut self, condition: &QueryCondition<T>) -> DbResult<Plan> {
+        Ok(self.execute(condition)?.1)
+    }
+
+    /// Recursively executes the condition.
+    ///
+    /// # Returns
+    ///
+    /// IDs of all matching records in ascending order and the plan which produced them.
+    fn execute(&mut self, condition: &QueryCondition<T>) -> DbResult<(Vec<u64>, Plan)> {
+        match condition {
+            QueryCondition::By(index, value) => {
+                let ids = index.search_ids(value.as_ref())?;
+                // Index lookups are estimated by the exact number of IDs stored under the key.
+                let plan = Plan::new(
+                    PlanOperation::IndexLookup {
+                        index: index.idx_name(),
+                    },
+                    ids.len(),
+                    ids.len(),
+                    vec![],
+                );
+
+                Ok((ids, plan))
+            }
+            QueryCondition::And(left, right) => match (left.as_ref(), right.as_ref()) {
+                // Apply filters as a residual predicate on the other side instead of scanning.
+                (QueryCondition::Filter(predicate), other)
+                | (other, QueryCondition::Filter(predicate)) => {
+                    let (ids, child) = self.execute(other)?;
+                    let ids = self.retain(ids, predicate)?;
+                    let plan = Plan::new(
+                        PlanOperation::Filter,
+                        child.estimated_rows,
+                        ids.len(),
+                        vec![child],
+                    );
+
+                    Ok((ids, plan))
+                }
+                // Subtract negated records from the other side instead of scanning.
+                (QueryCondition::Not(negated), other) | (other, QueryCondition::Not(negated)) => {
+                    let (ids, child) = self.execute(other)?;
+                    let estimated_rows = child.estimated_rows;
+
+                    let (ids, excluded) = if ids.is_empty() {
+                        (ids, self.skip(negated)?)
+                    } else {
+                        let (excluded_ids, excluded) = self.execute(negated)?;
+                        (difference(&ids, &excluded_ids), excluded)
+                    };
+
+                    let plan = Plan::new(
+                        PlanOperation::Difference,
+                        estimated_rows,
+                        ids.len(),
+                        vec![child, excluded],
+                    );
+
+                    Ok((ids, plan))
+                }
+                (left, right) => {
+                    // Evaluate the most selective side first, the other side can be skipped if it's empty.
+                    let (left_estimate, right_estimate) =
+                        (self.estimate(left)?, self.estimate(right)?);
+                    let (first, second) = if left_estimate <= right_estimate {
+                        (left, right)
+                    } else {
+                        (right, left)
+                    };
+
+                    let (ids, first) = self.execute(first)?;
+                    let (ids, second) = if ids.is_empty() {
+                        (ids, self.skip(second)?)
+                    } else {
+                        let (other_ids, second) = self.execute(second)?;
+                        (intersection(&ids, &other_ids), second)
+                    };
+
+                    let plan = Plan::new(
+                        PlanOperation::Intersect,
+                        left_estimate.min(right_estimate),
+                        ids.len(),
+                        vec![first, second],
+                    );
+
+                    Ok((ids, plan))
+                }
+            },
+            QueryCondition::Or(left, right) => {
+                let (left_ids, left) = self.execute(left)?;
+                let (right_ids, right) = self.execute(right)?;
+
+                let ids = union(&left_ids, &right_ids);
+                let estimate = left.estimated_rows.saturating_add(right.estimated_rows);
+                let plan = Plan::new(
+                    PlanOperation::Union,
+                    estimate.min(self.table_len()),
+                    ids.len(),
+                    vec![left, right],
+                );
+
+                Ok((ids, plan))
+            }
+            QueryCondition::Not(negated) => {
+                let all_ids = self.table.ids()?;
+                self.table_len = Some(all_ids.len());
+
+                let scan = Plan::new(PlanOperation::Scan, all_ids.len(), all_ids.len(), vec![]);
+                let (excluded_ids, excluded) = self.execute(negated)?;
+
+                let ids = difference(&all_ids, &excluded_ids);
+                let plan = Plan::new(
+                    PlanOperation::Difference,
+                    all_ids.len(),
+                    ids.len(),
+                    vec![scan, excluded],
+                );
+
+                Ok((ids, plan))
+            }
+            QueryCondition::Filter(predicate) => {
+                let mut ids = vec![];
+                let mut scanned = 0;
+
+                for record in self.table.iter() {
+                    let record = record?;
+                    scanned += 1;
+
+                    if predicate(&record.data) {
+                        ids.push(record.id);
+                        self.fetched.insert(record.id, record);
+                    }
+                }
+
+                self.table_len = Some(scanned);
+
+                let scan = Plan::new(PlanOperation::Scan, scanned, scanned, vec![]);
+                let plan = Plan::new(PlanOperation::Filter, scanned, ids.len(), vec![scan]);
+
+                Ok((ids, plan))
+            }
+        }
+    }
+
+    /// Plan for a condition which wasn't executed because its result couldn't affect the outcome.
+    fn skip(&mut self, condition: &QueryCondition<T>) -> DbResult<Plan> {
+        Ok(Plan {
+            operation: PlanOperation::Skipped,
+            estimated_rows: self.estimate(condition)?,
+            actual_rows: None,
+            children: vec![],
+        })
+    }
+
+    /// Keep the IDs of records which satisfy the predicate.
+    fn retain(&mut self, ids: Vec<u64>, predicate: &dyn Fn(&T) -> bool) -> DbResult<Vec<u64>> {
+        let mut retained = vec![];
+        for id in ids {
+            if let Some(record) = self.fetch(id)? {
+                if predicate(&record.data) {
+                    retained.push(id);
+                    self.fetched.insert(id, record);
+                }
+            }
+        }
+
+        Ok(retained)
+    }
+}
+
+/// Intersection of two sorted ID sets.
+fn intersection(left: &[u64], right: &[u64]) -> Vec<u64> {
+    let (mut i, mut j) = (0, 0);
+    let mut result = vec![];
+
+    while i < left.len() && j < right.len() {
+        match left[i].cmp(&right[j]) {
+            Ordering::Less => i += 1,
+            Ordering::Greater => j += 1,
+            Ordering::Equal => {
+                result.push(left[i]);
+                i += 1;
+                j += 1;
+            }
+        }
+    }
+
+    result
+}
+
+/// Union of two sorted ID sets.
+fn union(left: &[u64], right: &[u64]) -> Vec<u64> {
+    let (mut i, mut j) = (0, 0);
+    let mut result = Vec::with_capacity(left.len() + right.len());
+
+    while i < left.len() && j < right.len() {
+        match left[i].cmp(&right[j]) {
+            Ordering::Less => {
+                result.push(left[i]);
+                i += 1;
+            }
+            Ordering::Greater => {
+                result.push(right[j]);
+                j += 1;
+            }
+            Ordering::Equal => {
+                result.push(left[i]);
+                i += 1;
+                j += 1;
+            }
+        }
+    }
+
+    result.extend_from_slice(&left[i..]);
+    result.extend_from_slice(&right[j..]);
+    result
+}
+
+/// IDs of the sorted set `left` which are not in the sorted set `right`.
+fn difference(left: &[u64], right: &[u64]) -> Vec<u64> {
+    let mut j = 0;
+    let mut result = vec![];
+
+    for id in left {
+        while j < right.len() && right[j] < *id {
+            j += 1;
+        }
+
+        if j >= right.len() || right[j] != *id {
+            result.push(*id);
+        }
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ConditionBuilder, QueryBuilder, TinyBase};
+
+    #[test]
+    fn planner_set_operations() {
+        assert_eq!(intersection(&[1, 3, 5, 7], &[2, 3, 7, 9]), vec![3, 7]);
+        assert_eq!(union(&[1, 3, 5], &[2, 3, 9]), vec![1, 2, 3, 5, 9]);
+        assert_eq!(difference(&[1, 3, 5, 7], &[3, 4, 7]), vec![1, 5]);
+        assert!(intersection(&[], &[1]).is_empty());
+    }
+
+    #[test]
+    fn planner_estimate_and_select() {
+        let db = TinyBase::new(None, true);
+        let table: Table<(String, u8)> = db.open_table("test_table").unwrap();
+
+        let name = table
+            .create_index("name", |(name, _)| name.to_owned())
+            .unwrap();
+        let age = table.create_index("age", |(_, age)| *age).unwrap();
+
+        let john = table.insert(("John".to_string(), 18)).unwrap();
+        table.insert(("Bill".to_string(), 18)).unwrap();
+        table.insert(("Anna".to_string(), 18)).unwrap();
+        table.insert(("John".to_string(), 40)).unwrap();
+
+        let condition = ConditionBuilder::and(
+            ConditionBuilder::by(&age, 18),
+            ConditionBuilder::by(&name, "John".to_string()),
+        )
+        .build();
+
+        let mut planner = Planner::new(&table);
+        assert_eq!(
+            planner
+                .estimate(&ConditionBuilder::by(&age, 18).build())
+                .unwrap(),
+            3
+        );
+        assert_eq!(planner.estimate(&condition).unwrap(), 2);
+        assert_eq!(planner.select_ids(&condition).unwrap(), vec![john]);
+
+        let scan = ConditionBuilder::filter(|(_, age): &(String, u8)| *age > 30).build();
+        assert_eq!(planner.estimate(&scan).unwrap(), 4);
+
+        // Unions never estimate more records than the table holds.
+        let union = ConditionBuilder::or(
+            ConditionBuilder::by(&age, 18),
+            ConditionBuilder::filter(|(_, age): &(String, u8)| *age > 30),
+        )
+        .build();
+        assert_eq!(planner.estimate(&union).unwrap(), 4);
+    }
+
+    #[test]
+    fn planner_explain() {
+        let db = TinyBase::new(None, true);
+        let table: Table<(String, u8)> = db.open_table("test_table").unwrap();
+
+        let name = table
+            .create_index("name", |(name, _)| name.to_owned())
+            .unwrap();
+        let age = table.create_index("age", |(_, age)| *age).unwrap();
+
+        table.insert(("John".to_string(), 18)).unwrap();
+        table.insert(("Bill".to_string(), 18)).unwrap();
+        table.insert(("John".to_string(), 40)).unwrap();
+
+        let plan = QueryBuilder::new(&table)
+            .with_condition(ConditionBuilder::and(
+                ConditionBuilder::by(&age, 18),
+                ConditionBuilder::by(&name, "Anna".to_string()),
+            ))
+            .explain()
+            .unwrap();
+
+        // The empty lookup is evaluated first and the other side is skipped.
+        assert_eq!(plan.operation, PlanOperation::Intersect);
+        assert_eq!(plan.actual_rows, Some(0));
+        assert_eq!(plan.children[1].operation, PlanOperation::Skipped);
+        assert_eq!(plan.children[1].estimated_rows, 2);
+        assert_eq!(plan.indexes(), vec!["test_table_idx_name"]);
+        assert!(!plan.used_scan());
+
+        let plan = QueryBuilder::new(&table)
+            .with_condition(ConditionBuilder::or(
+                ConditionBuilder::by(&name, "Bill".to_string()),
+                ConditionBuilder::filter(|(_, age): &(String, u8)| *age > 30),
+            ))
+            .explain()
+            .unwrap();
+
+        assert!(plan.used_scan());
+        assert_eq!(
+            plan.to_string(),
+            [
+                "Union (estimated 3, actual 2)",
+                "  IndexLookup test_table_idx_name (estimated 1, actual 1)",
+                "  Filter (estimated 3, actual 1)",
+                "    Scan (estimated 3, actual 3)",
+                "",
+            ]
+            .join("\n")
+        );
+    }
+}
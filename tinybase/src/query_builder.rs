@@ -1,8 +1,11 @@
 use std::any::Any;
+use std::cmp;
+use std::ops::Bound;
 
 use crate::{
     index::{AnyIndex, Index, IndexType},
-    result::DbResult,
+    planner::{Plan, Planner},
+    result::{DbResult, TinyBaseError},
     table::{Table, TableType},
     Record,
 };
@@ -12,9 +15,28 @@ pub enum QueryCondition<T>
 where
     T: TableType + 'static,
 {
-    By(Box<dyn AnyIndex<T>>, Box<dyn Any>),
+    By(Box<dyn AnyIndex<T>>, Box<dyn Any + Send + Sync>),
     And(Box<QueryCondition<T>>, Box<QueryCondition<T>>),
     Or(Box<QueryCondition<T>>, Box<QueryCondition<T>>),
+    /// Negation of a condition.
+    /// When not combined with another condition through `And`, this requires a full table scan.
+    Not(Box<QueryCondition<T>>),
+    /// Arbitrary predicate on the record data.
+    /// When not combined with an indexed condition through `And`, this requires a full table scan.
+    Filter(Box<dyn Fn(&T) -> bool + Send + Sync>),
+}
+
+impl<T: TableType + 'static> QueryCondition<T> {
+    /// Recursively evaluates the condition against the data of a single record.
+    pub(crate) fn matches(&self, data: &T) -> DbResult<bool> {
+        Ok(match self {
+            QueryCondition::By(index, value) => index.key_matches(value.as_ref(), data)?,
+            QueryCondition::And(left, right) => left.matches(data)? && right.matches(data)?,
+            QueryCondition::Or(left, right) => left.matches(data)? || right.matches(data)?,
+            QueryCondition::Not(negated) => !negated.matches(data)?,
+            QueryCondition::Filter(predicate) => predicate(data),
+        })
+    }
 }
 
 /// For building and chaining query conditions.
@@ -51,6 +73,42 @@ impl<T: TableType + 'static> ConditionBuilder<T> {
         Self(QueryCondition::Or(Box::new(left.0), Box::new(right.0)))
     }
 
+    /// Creates a new query condition representing the logical NOT of an existing condition.
+    ///
+    /// When combined with another condition using [`ConditionBuilder::and`], the negated records
+    /// are subtracted from the other side. Otherwise the whole table is scanned.
+    ///
+    /// # Arguments
+    ///
+    /// * `condition` - The condition to negate.
+    #[allow(clippy::should_implement_trait)]
+    pub fn not(condition: Self) -> Self {
+        Self(QueryCondition::Not(Box::new(condition.0)))
+    }
+
+    /// Creates a new query condition matching records of `left` which don't match `right`.
+    ///
+    /// # Arguments
+    ///
+    /// * `left` - The condition to select records by.
+    /// * `right` - The condition which excludes records from `left`.
+    pub fn and_not(left: Self, right: Self) -> Self {
+        Self::and(left, Self::not(right))
+    }
+
+    /// Creates a new query condition which matches records satisfying the given predicate.
+    ///
+    /// When combined with an indexed condition using [`ConditionBuilder::and`], the predicate is
+    /// only evaluated against records selected by the index. Otherwise the whole table is scanned.
+    /// The predicate must be `Send + Sync`, see [`TableType`].
+    ///
+    /// # Arguments
+    ///
+    /// * `predicate` - A function that takes a reference to the value `T` and returns a boolean indicating if it matches.
+    pub fn filter(predicate: impl Fn(&T) -> bool + Send + Sync + 'static) -> Self {
+        Self(QueryCondition::Filter(Box::new(predicate)))
+    }
+
     /// Builds the final query condition.
     ///
     /// # Returns
@@ -61,12 +119,88 @@ impl<T: TableType + 'static> ConditionBuilder<T> {
     }
 }
 
-impl<T: TableType + 'static> Into<QueryCondition<T>> for ConditionBuilder<T> {
-    fn into(self) -> QueryCondition<T> {
-        self.build()
+impl<T: TableType + 'static> From<ConditionBuilder<T>> for QueryCondition<T> {
+    fn from(val: ConditionBuilder<T>) -> Self {
+        val.build()
     }
 }
 
+/// Direction in which query results are ordered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Order {
+    /// Smallest key first.
+    Asc,
+    /// Largest key first.
+    Desc,
+}
+
+/// Comparison between the data of two records.
+type Comparator<T> = Box<dyn Fn(&T, &T) -> cmp::Ordering + Send + Sync>;
+
+/// What query results are ordered by.
+enum Ordering<T: TableType + 'static> {
+    /// Ordered by the key of an index.
+    Index(Box<dyn AnyIndex<T>>, Order),
+    /// Ordered by a comparison on the record data.
+    Key(Comparator<T>, Order),
+}
+
+/// Opaque position in a paginated query, pointing after the last record of a [`Page`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cursor {
+    /// Index and direction the query is ordered by, empty when it is ordered by ID, so the cursor
+    /// can't resume a query with another ordering.
+    ordering: String,
+    /// Encoded index key of the last record, empty when the query is not ordered by an index.
+    key: Vec<u8>,
+    /// ID of the last record.
+    id: u64,
+}
+
+impl Cursor {
+    /// Serialize the cursor so it can be handed out and resumed later.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = (self.ordering.len() as u32).to_be_bytes().to_vec();
+        bytes.extend_from_slice(self.ordering.as_bytes());
+        bytes.extend_from_slice(&self.key);
+        bytes.extend_from_slice(&self.id.to_be_bytes());
+        bytes
+    }
+
+    /// Deserialize a cursor previously produced by [`Cursor::to_bytes`].
+    ///
+    /// # Arguments
+    ///
+    /// * `bytes` - The serialized cursor.
+    pub fn from_bytes(bytes: &[u8]) -> DbResult<Self> {
+        let invalid = || TinyBaseError::QueryBuilder("Invalid cursor".into());
+
+        let (len, rest) = bytes.split_first_chunk::<4>().ok_or_else(invalid)?;
+        let len = u32::from_be_bytes(*len) as usize;
+        if rest.len() < len + 8 {
+            return Err(invalid());
+        }
+
+        let (ordering, rest) = rest.split_at(len);
+        let (key, id) = rest.split_at(rest.len() - 8);
+
+        Ok(Self {
+            ordering: String::from_utf8(ordering.to_vec()).map_err(|_| invalid())?,
+            key: key.to_vec(),
+            id: u64::from_be_bytes(id.try_into().unwrap()),
+        })
+    }
+}
+
+/// A single page of query results.
+#[derive(Debug, Clone)]
+pub struct Page<T> {
+    /// Records in this page.
+    pub records: Vec<Record<T>>,
+    /// Cursor to resume after this page, or [`None`] if there are no more results.
+    pub cursor: Option<Cursor>,
+}
+
 /// Builder for building and executing queries.
 pub struct QueryBuilder<T>
 where
@@ -74,6 +208,9 @@ where
 {
     table: Table<T>,
     condition: Option<QueryCondition<T>>,
+    ordering: Option<Ordering<T>>,
+    offset: usize,
+    limit: Option<usize>,
 }
 
 impl<T> QueryBuilder<T>
@@ -89,6 +226,9 @@ where
         Self {
             table: table.clone(),
             condition: None,
+            ordering: None,
+            offset: 0,
+            limit: None,
         }
     }
 
@@ -103,6 +243,58 @@ where
         self
     }
 
+    /// Orders the results by the key of an index.
+    /// This will overwrite the previous ordering (if set).
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - The index whose key the results are ordered by.
+    /// * `order` - The direction of the ordering.
+    pub fn order_by<I: IndexType + 'static>(mut self, index: &Index<T, I>, order: Order) -> Self {
+        self.ordering = Some(Ordering::Index(Box::new(index.clone()), order));
+        self
+    }
+
+    /// Orders the results by a key computed from each record.
+    /// This will overwrite the previous ordering (if set).
+    /// The key function must be `Send + Sync`, see [`TableType`].
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - A function which computes the key to order each record by.
+    /// * `order` - The direction of the ordering.
+    pub fn order_by_key<K: Ord>(
+        mut self,
+        key: impl Fn(&T) -> K + Send + Sync + 'static,
+        order: Order,
+    ) -> Self {
+        self.ordering = Some(Ordering::Key(
+            Box::new(move |left, right| key(left).cmp(&key(right))),
+            order,
+        ));
+        self
+    }
+
+    /// Skips the first `offset` results.
+    ///
+    /// # Arguments
+    ///
+    /// * `offset` - The number of results to skip.
+    pub fn offset(mut self, offset: usize) -> Self {
+        self.offset = offset;
+        self
+    }
+
+    /// Limits the number of results.
+    ///
+    /// # Arguments
+    ///
+    /// * `limit` - The maximum number of results.
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
     /// Validates the query builder's state.
     fn check_valid(&self) -> DbResult<()> {
         match &self.condition {
@@ -119,8 +311,19 @@ where
     ///
     /// All selected [`Record`] instances.
     pub fn select(self) -> DbResult<Vec<Record<T>>> {
+        self.execute()
+    }
+
+    /// Executes the query condition and describes how it was executed.
+    ///
+    /// Ordering, offset and limit are not part of the plan.
+    ///
+    /// # Returns
+    ///
+    /// The [`Plan`] tree of the query, which can be rendered as text with its [`std::fmt::Display`] implementation.
+    pub fn explain(self) -> DbResult<Plan> {
         self.check_valid()?;
-        Self::select_recursive(self.condition.unwrap())
+        Planner::new(&self.table).explain(self.condition.as_ref().unwrap())
     }
 
     /// Updates the records in the table based on the query condition and new value.
@@ -133,13 +336,10 @@ where
     ///
     /// All updated [`Record`] instances.
     pub fn update(self, updater: fn(T) -> T) -> DbResult<Vec<Record<T>>> {
-        self.check_valid()?;
-        let ids: Vec<u64> = Self::select_recursive(self.condition.unwrap())?
-            .iter()
-            .map(|record| record.id)
-            .collect();
+        let table = self.table.clone();
+        let ids: Vec<u64> = self.execute()?.iter().map(|record| record.id).collect();
 
-        self.table.update(&ids, updater)
+        table.update(&ids, updater)
     }
 
     /// Deletes the records from the table based on the query condition.
@@ -148,13 +348,13 @@ where
     ///
     /// All deleted [`Record`] instances.
     pub fn delete(self) -> DbResult<Vec<Record<T>>> {
-        self.check_valid()?;
-        let selected = Self::select_recursive(self.condition.unwrap())?;
+        let table = self.table.clone();
+        let selected = self.execute()?;
 
         let mut removed = vec![];
 
         for record in &selected {
-            if let Some(record) = self.table.delete(record.id)? {
+            if let Some(record) = table.delete(record.id)? {
                 removed.push(record);
             }
         }
@@ -162,40 +362,178 @@ where
         Ok(removed)
     }
 
-    /// Recursively processes the query conditions and returns the selected records.
-    fn select_recursive(condition: QueryCondition<T>) -> DbResult<Vec<Record<T>>> {
-        match condition {
-            QueryCondition::By(index, value) => index.search(value),
-            QueryCondition::And(left, right) => {
-                let left_records = Self::select_recursive(*left)?;
-                let right_records = Self::select_recursive(*right)?;
+    /// Executes the query and returns a single page of results following `cursor`.
+    ///
+    /// Unlike `offset`, the position is stored as the last index key and record ID, so pages stay
+    /// stable while records are inserted concurrently. The query may be ordered by an index, otherwise
+    /// results are ordered by ID. If no condition is set, every record in the table is paginated.
+    /// `offset` and `limit` are ignored.
+    ///
+    /// # Arguments
+    ///
+    /// * `cursor` - The cursor of the previous page, or [`None`] for the first page. Cursors of
+    ///   queries with another ordering are rejected.
+    /// * `n` - The maximum number of records in the page.
+    ///
+    /// # Returns
+    ///
+    /// A [`Page`] containing the selected records and the cursor for the next page.
+    pub fn page_after(self, cursor: Option<&Cursor>, n: usize) -> DbResult<Page<T>> {
+        let mut page = Page {
+            records: vec![],
+            cursor: None,
+        };
+
+        if n == 0 {
+            return Ok(page);
+        }
 
-                let mut intersection: Vec<Record<T>> = left_records.clone();
-                intersection.retain(|record| {
-                    right_records
-                        .iter()
-                        .any(|other_record| record.id == other_record.id)
-                });
+        let ordering = match &self.ordering {
+            Some(Ordering::Key(..)) => {
+                return Err(TinyBaseError::QueryBuilder(
+                    "Cursor pagination requires ordering by an index".into(),
+                ))
+            }
+            Some(Ordering::Index(index, Order::Asc)) => format!("{}:asc", index.idx_name()),
+            Some(Ordering::Index(index, Order::Desc)) => format!("{}:desc", index.idx_name()),
+            None => String::new(),
+        };
+        if cursor.is_some_and(|cursor| cursor.ordering != ordering) {
+            return Err(TinyBaseError::QueryBuilder(
+                "Cursor belongs to a query with another ordering".into(),
+            ));
+        }
+
+        let mut last_key = vec![];
+
+        match &self.ordering {
+            // Rejected above.
+            Some(Ordering::Key(..)) => unreachable!(),
+            Some(Ordering::Index(index, order)) => {
+                let reverse = *order == Order::Desc;
+                let start = match cursor {
+                    Some(cursor) => Bound::Included(cursor.key.clone()),
+                    None => Bound::Unbounded,
+                };
+
+                'entries: for entry in index.entries(start, reverse)? {
+                    let (key, mut ids) = entry?;
+                    if reverse {
+                        ids.reverse();
+                    }
 
-                Ok(intersection)
+                    for id in ids {
+                        // Skip records up to and including the cursor within the cursor's key.
+                        if let Some(cursor) = cursor.filter(|cursor| cursor.key == *key) {
+                            if (!reverse && id <= cursor.id) || (reverse && id >= cursor.id) {
+                                continue;
+                            }
+                        }
+
+                        if let Some(record) = self.table.select(id)? {
+                            if self.matches(&record)? {
+                                page.records.push(record);
+                                last_key = key.to_vec();
+
+                                if page.records.len() == n {
+                                    break 'entries;
+                                }
+                            }
+                        }
+                    }
+                }
             }
-            QueryCondition::Or(left, right) => {
-                let mut records: Vec<Record<T>> =
-                    Self::select_recursive(*left)?.into_iter().collect();
-                records.extend(Self::select_recursive(*right)?.into_iter());
-
-                let mut seen = Vec::new();
-                records.retain(|item| {
-                    if seen.contains(&item.id) {
-                        false
-                    } else {
-                        seen.push(item.id);
-                        true
+            None => {
+                let records = match cursor {
+                    Some(cursor) => self.table.iter_after(cursor.id)?,
+                    None => self.table.iter(),
+                };
+
+                for record in records {
+                    let record = record?;
+                    if self.matches(&record)? {
+                        page.records.push(record);
+
+                        if page.records.len() == n {
+                            break;
+                        }
                     }
+                }
+            }
+        }
+
+        if page.records.len() == n {
+            page.cursor = page.records.last().map(|record| Cursor {
+                ordering,
+                key: last_key,
+                id: record.id,
+            });
+        }
+
+        Ok(page)
+    }
+
+    /// Checks if a single record satisfies the query condition.
+    fn matches(&self, record: &Record<T>) -> DbResult<bool> {
+        match &self.condition {
+            Some(condition) => condition.matches(&record.data),
+            None => Ok(true),
+        }
+    }
+
+    /// Selects the records matching the condition, then applies ordering, offset and limit.
+    fn execute(self) -> DbResult<Vec<Record<T>>> {
+        self.check_valid()?;
+
+        let mut planner = Planner::new(&self.table);
+        let ids = planner.select_ids(self.condition.as_ref().unwrap())?;
+
+        // Without an ordering, pagination can be applied before any record is fetched.
+        if self.ordering.is_none() {
+            return ids
+                .into_iter()
+                .skip(self.offset)
+                .take(self.limit.unwrap_or(usize::MAX))
+                .filter_map(|id| planner.fetch(id).transpose())
+                .collect();
+        }
+
+        let mut records = ids
+            .into_iter()
+            .filter_map(|id| planner.fetch(id).transpose())
+            .collect::<DbResult<Vec<_>>>()?;
+
+        match self.ordering {
+            Some(Ordering::Index(index, order)) => {
+                let mut keyed = records
+                    .into_iter()
+                    .map(|record| Ok((index.gen_key(&record.data)?, record)))
+                    .collect::<DbResult<Vec<_>>>()?;
+
+                keyed.sort_by(|(left_key, left), (right_key, right)| {
+                    Self::directed(left_key.cmp(right_key), order).then(left.id.cmp(&right.id))
                 });
 
-                Ok(records)
+                records = keyed.into_iter().map(|(_, record)| record).collect();
             }
+            Some(Ordering::Key(compare, order)) => records.sort_by(|left, right| {
+                Self::directed(compare(&left.data, &right.data), order).then(left.id.cmp(&right.id))
+            }),
+            None => {}
+        }
+
+        Ok(records
+            .into_iter()
+            .skip(self.offset)
+            .take(self.limit.unwrap_or(usize::MAX))
+            .collect())
+    }
+
+    /// Applies the order direction to a comparison.
+    fn directed(ordering: cmp::Ordering, order: Order) -> cmp::Ordering {
+        match order {
+            Order::Asc => ordering,
+            Order::Desc => ordering.reverse(),
         }
     }
 }
@@ -298,6 +636,206 @@ mod tests {
         assert_eq!(selected_records.len(), 2);
     }
 
+    #[test]
+    fn query_builder_select_filter() {
+        let db = TinyBase::new(None, true);
+        let table: Table<(String, u8)> = db.open_table("test_table").unwrap();
+
+        // Create an index for the table
+        let last_name = table
+            .create_index("last_name", |(last_name, _)| last_name.to_owned())
+            .unwrap();
+
+        // Insert values into the table
+        table.insert(("Smith".to_string(), 18)).unwrap();
+        let old_smith = table.insert(("Smith".to_string(), 40)).unwrap();
+        table.insert(("Jones".to_string(), 50)).unwrap();
+
+        // Indexed lookup combined with a residual predicate.
+        let smiths = QueryBuilder::new(&table)
+            .with_condition(ConditionBuilder::and(
+                ConditionBuilder::by(&last_name, "Smith".to_string()),
+                ConditionBuilder::filter(|(_, age)| *age > 30),
+            ))
+            .select()
+            .expect("Select failed");
+
+        assert_eq!(smiths.len(), 1);
+        assert_eq!(smiths[0].id, old_smith);
+
+        // Filter without an index falls back to a full scan.
+        let older = QueryBuilder::new(&table)
+            .with_condition(ConditionBuilder::filter(|(_, age)| *age > 30))
+            .select()
+            .expect("Select failed");
+
+        assert_eq!(older.len(), 2);
+    }
+
+    #[test]
+    fn query_builder_select_not() {
+        let db = TinyBase::new(None, true);
+        let table: Table<(String, String)> = db.open_table("test_table").unwrap();
+
+        // Create indexes for the table
+        let name = table
+            .create_index("name", |(name, _)| name.to_owned())
+            .unwrap();
+        let last_name = table
+            .create_index("last_name", |(_, last_name)| last_name.to_owned())
+            .unwrap();
+
+        // Insert values into the table
+        table
+            .insert(("John".to_string(), "Smith".to_string()))
+            .unwrap();
+        let bill = table
+            .insert(("Bill".to_string(), "Smith".to_string()))
+            .unwrap();
+        let coraline = table
+            .insert(("Coraline".to_string(), "Jones".to_string()))
+            .unwrap();
+
+        // Smiths who are not named John.
+        let smiths = QueryBuilder::new(&table)
+            .with_condition(ConditionBuilder::and_not(
+                ConditionBuilder::by(&last_name, "Smith".to_string()),
+                ConditionBuilder::by(&name, "John".to_string()),
+            ))
+            .select()
+            .expect("Select failed");
+
+        assert_eq!(smiths.len(), 1);
+        assert_eq!(smiths[0].id, bill);
+
+        // Bare negation scans the whole table.
+        let not_smiths = QueryBuilder::new(&table)
+            .with_condition(ConditionBuilder::not(ConditionBuilder::by(
+                &last_name,
+                "Smith".to_string(),
+            )))
+            .select()
+            .expect("Select failed");
+
+        assert_eq!(not_smiths.len(), 1);
+        assert_eq!(not_smiths[0].id, coraline);
+    }
+
+    #[test]
+    fn query_builder_order_limit_offset() {
+        let db = TinyBase::new(None, true);
+        let table: Table<(String, u8)> = db.open_table("test_table").unwrap();
+
+        // Create an index for the table
+        let age = table.create_index("age", |(_, age)| *age).unwrap();
+
+        // Insert values into the table
+        for (name, years) in [("John", 18), ("Bill", 40), ("Coraline", 16), ("Anna", 30)] {
+            table.insert((name.to_string(), years)).unwrap();
+        }
+
+        let names = |records: Vec<Record<(String, u8)>>| -> Vec<String> {
+            records.into_iter().map(|record| record.data.0).collect()
+        };
+
+        let everyone = || ConditionBuilder::filter(|_: &(String, u8)| true);
+
+        let by_age = QueryBuilder::new(&table)
+            .with_condition(everyone())
+            .order_by(&age, Order::Desc)
+            .select()
+            .expect("Select failed");
+        assert_eq!(names(by_age), vec!["Bill", "Anna", "John", "Coraline"]);
+
+        let page = QueryBuilder::new(&table)
+            .with_condition(everyone())
+            .order_by_key(|(name, _)| name.to_owned(), Order::Asc)
+            .offset(1)
+            .limit(2)
+            .select()
+            .expect("Select failed");
+        assert_eq!(names(page), vec!["Bill", "Coraline"]);
+
+        let oldest = QueryBuilder::new(&table)
+            .with_condition(ConditionBuilder::filter(|(_, age)| *age > 17))
+            .order_by(&age, Order::Desc)
+            .limit(1)
+            .delete()
+            .expect("Delete failed");
+        assert_eq!(names(oldest), vec!["Bill"]);
+        assert_eq!(table.len(), 3);
+    }
+
+    #[test]
+    fn query_builder_page_after() {
+        let db = TinyBase::new(None, true);
+        let table: Table<(String, u8)> = db.open_table("test_table").unwrap();
+
+        // Create an index for the table
+        let age = table.create_index("age", |(_, age)| *age).unwrap();
+
+        // Insert values into the table, some of which share an index key
+        for (name, years) in [("John", 18), ("Bill", 40), ("Anna", 18), ("Coraline", 16)] {
+            table.insert((name.to_string(), years)).unwrap();
+        }
+
+        let names = |page: &Page<(String, u8)>| -> Vec<String> {
+            page.records
+                .iter()
+                .map(|record| record.data.0.clone())
+                .collect()
+        };
+
+        let first = QueryBuilder::new(&table)
+            .order_by(&age, Order::Asc)
+            .page_after(None, 2)
+            .unwrap();
+        assert_eq!(names(&first), vec!["Coraline", "John"]);
+
+        // Inserting before the cursor doesn't shift the next page.
+        table.insert(("Zed".to_string(), 1)).unwrap();
+
+        let cursor = Cursor::from_bytes(&first.cursor.unwrap().to_bytes()).unwrap();
+        let second = QueryBuilder::new(&table)
+            .order_by(&age, Order::Asc)
+            .page_after(Some(&cursor), 2)
+            .unwrap();
+        assert_eq!(names(&second), vec!["Anna", "Bill"]);
+
+        let last = QueryBuilder::new(&table)
+            .order_by(&age, Order::Asc)
+            .page_after(second.cursor.as_ref(), 2)
+            .unwrap();
+        assert!(last.records.is_empty());
+        assert!(last.cursor.is_none());
+
+        // Table scans with a condition are ordered by ID.
+        let adults = QueryBuilder::new(&table)
+            .with_condition(ConditionBuilder::filter(|(_, age)| *age >= 18))
+            .page_after(None, 2)
+            .unwrap();
+        assert_eq!(names(&adults), vec!["John", "Bill"]);
+
+        let adults = QueryBuilder::new(&table)
+            .with_condition(ConditionBuilder::filter(|(_, age)| *age >= 18))
+            .page_after(adults.cursor.as_ref(), 2)
+            .unwrap();
+        assert_eq!(names(&adults), vec!["Anna"]);
+        assert!(adults.cursor.is_none());
+
+        // Cursors only resume queries with the same ordering.
+        for query in [
+            QueryBuilder::new(&table).order_by(&age, Order::Desc),
+            QueryBuilder::new(&table),
+        ] {
+            assert!(matches!(
+                query.page_after(Some(&cursor), 2),
+                Err(TinyBaseError::QueryBuilder(_))
+            ));
+        }
+        assert!(Cursor::from_bytes(&[0, 0, 0, 9, 1]).is_err());
+    }
+
     #[test]
     fn query_builder_update() {
         let db = TinyBase::new(None, true);
@@ -6,6 +6,8 @@ pub enum TinyBaseError {
     Sled(#[from] sled::Error),
     #[error("serializer error")]
     Serializer(#[from] bincode::Error),
+    #[error("key encoding error: {0}")]
+    KeyEncoding(#[source] Box<dyn std::error::Error + Send + Sync>),
     #[error("record failed to match unique constraint")]
     Exists { constraint: String, id: u64 },
     #[error("a condition check was not met")]
@@ -14,6 +16,56 @@ pub enum TinyBaseError {
     QueryBuilder(String),
     #[error("batch operation violates constraints")]
     BatchOperationConstraints,
+    #[error("database is opened read-only")]
+    ReadOnly,
+    #[error("database not found at {0}")]
+    NotFound(String),
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("backup error: {0}")]
+    Backup(#[source] Box<dyn std::error::Error + Send + Sync>),
+    #[error("id {0} is the largest id, which leaves none to generate")]
+    IdOutOfRange(u64),
+    #[error("json error: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("codec error: {0}")]
+    Codec(#[source] Box<dyn std::error::Error + Send + Sync>),
+    #[error("table {table} is stored with the {stored} codec, not {requested}")]
+    CodecMismatch {
+        table: String,
+        stored: String,
+        requested: String,
+    },
+    #[error("table {table} has schema version {stored}, which is newer than {requested}")]
+    SchemaVersion {
+        table: String,
+        stored: u32,
+        requested: u32,
+    },
+    #[error("table {table} has no migration from schema version {version}")]
+    MissingMigration { table: String, version: u32 },
+    #[error("a record with id {0} already exists")]
+    IdExists(u64),
+    #[error("table {table} has the type fingerprint {stored}, not {requested}")]
+    IncompatibleType {
+        table: String,
+        stored: String,
+        requested: String,
+    },
+    #[error("table {0} doesn't exist")]
+    TableNotFound(String),
+    #[error("table {0} already exists")]
+    TableExists(String),
+    #[error("table {0} is still open")]
+    TableInUse(String),
+    #[error("invalid json path {0}")]
+    JsonPath(String),
+    #[cfg(feature = "csv")]
+    #[error("csv error: {0}")]
+    Csv(#[from] csv::Error),
+    #[cfg(feature = "csv")]
+    #[error("invalid csv header: {0}")]
+    CsvHeader(String),
 }
 
 pub type DbResult<T> = Result<T, TinyBaseError>;
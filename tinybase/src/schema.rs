@@ -0,0 +1,564 @@
+use std::any;
+use std::collections::HashMap;
+use std::ops::Bound;
+use std::sync::{Arc, Weak};
+
+use serde::de::DeserializeOwned;
+use sled::transaction::Transactional;
+use sled::{Db, IVec, Tree};
+
+use crate::batch::transaction_error;
+use crate::catalog::{self, TableInfo, CATALOG_TREE};
+use crate::codec::{Bincode, Codec, RecordCodec};
+use crate::encoding::encode;
+use crate::result::{DbResult, TinyBaseError};
+use crate::table::{SharedTable, TableInner, TableType, META_TREE};
+use crate::{Table, TinyBase};
+
+/// Schema version of tables which never set one, and of records without a recorded version.
+pub const INITIAL_VERSION: u32 = 1;
+
+/// Length of the schema version which the encoded data of each record starts with.
+const VERSION_LEN: usize = 4;
+
+/// Number of records converted per transaction by [`register_legacy`].
+const LEGACY_BATCH_SIZE: usize = 1000;
+
+/// Upgrades the encoded data of a record from an older schema version.
+type Migration<T> = Box<dyn Fn(&[u8]) -> DbResult<T> + Send + Sync>;
+
+/// How the records of a table are serialized, and how records of older versions are upgraded.
+///
+/// Records are stored as their schema version followed by their data encoded with the codec, so
+/// the version of a record is known without another lookup.
+pub(crate) struct Schema<T> {
+    codec: Arc<dyn RecordCodec<T>>,
+    /// Version of newly written records.
+    pub(crate) version: u32,
+    /// Whether records start with their version, which is only not the case for tables written
+    /// before the catalog existed and opened read-only.
+    versioned: bool,
+    migrations: HashMap<u32, Migration<T>>,
+    /// Whether records of every version are decoded as they are, instead of being migrated.
+    dynamic: bool,
+    table: String,
+}
+
+impl<T: TableType> Schema<T> {
+    /// Open the schema of a table, recording its type, codec and version in the catalog.
+    ///
+    /// # Arguments
+    ///
+    /// * `builder` - The configuration of the table.
+    /// * `first` - Whether no other handle of the table is open, in which case the indexes and
+    ///   constraints recorded in the catalog are cleared for the table to register them again.
+    ///
+    /// # Errors
+    ///
+    /// [`TinyBaseError::CodecMismatch`] if the table was created with another codec,
+    /// [`TinyBaseError::SchemaVersion`] if the table already has a newer version, and
+    /// [`TinyBaseError::IncompatibleType`] if the table was opened with another fingerprint at
+    /// the same version. Dynamic schemas take the version of the table instead, so only the codec
+    /// has to match.
+    fn open<C: Codec>(builder: TableBuilder<'_, T, C>, first: bool) -> DbResult<Self> {
+        let TableBuilder {
+            db,
+            name,
+            codec,
+            mut version,
+            mut fingerprint,
+            migrations,
+            dynamic,
+        } = builder;
+        let (engine, read_only) = (&db.engine, db.read_only);
+        let mut type_name = any::type_name::<T>().to_owned();
+
+        let root = engine.open_tree(&name)?;
+        let registered = catalog::get(engine, &name)?;
+        let legacy = registered.is_none() && !root.is_empty();
+        let stored = match registered.clone() {
+            Some(info) => Some(info),
+            // Tables written before the catalog existed hold bincode records of the initial
+            // version without the version, and their type is unknown.
+            None if legacy => Some(TableInfo {
+                type_name: type_name.clone(),
+                codec: Bincode.name().to_owned(),
+                version: INITIAL_VERSION,
+                ..TableInfo::new(&name)
+            }),
+            None => None,
+        };
+
+        // A table which was partially converted holds records both with and without a version,
+        // so it can only be read once the conversion is finished.
+        if legacy
+            && read_only
+            && engine
+                .open_tree(META_TREE)?
+                .contains_key(legacy_key(&name))?
+        {
+            return Err(TinyBaseError::ReadOnly);
+        }
+
+        if let (Some(stored), true) = (&stored, dynamic) {
+            type_name = stored.type_name.clone();
+            version = stored.version;
+        }
+
+        if let Some(stored) = &stored {
+            if stored.codec != codec.name() {
+                return Err(TinyBaseError::CodecMismatch {
+                    table: name,
+                    stored: stored.codec.clone(),
+                    requested: codec.name().to_owned(),
+                });
+            }
+
+            if stored.version > version {
+                return Err(TinyBaseError::SchemaVersion {
+                    table: name,
+                    stored: stored.version,
+                    requested: version,
+                });
+            }
+
+            // The type may only change along with the version, when records can be migrated.
+            if stored.version == version {
+                match (&stored.fingerprint, &fingerprint) {
+                    (Some(stored), Some(requested)) if stored != requested => {
+                        return Err(TinyBaseError::IncompatibleType {
+                            table: name,
+                            stored: stored.clone(),
+                            requested: requested.clone(),
+                        });
+                    }
+                    // Opening the table without a fingerprint keeps the recorded one.
+                    (Some(_), None) => fingerprint = stored.fingerprint.clone(),
+                    _ => {}
+                }
+            }
+        }
+
+        if !read_only {
+            let describe = |info: &mut TableInfo| {
+                info.type_name = type_name.clone();
+                info.fingerprint = fingerprint.clone();
+                info.codec = codec.name().to_owned();
+                info.version = version;
+
+                if first {
+                    info.indexes.clear();
+                    info.constraints.clear();
+                }
+            };
+
+            if registered.is_some() {
+                // Updated in place, so indexes registered concurrently aren't lost.
+                catalog::update(engine, &name, describe)?;
+            } else {
+                let mut info = stored.unwrap_or_else(|| TableInfo::new(&name));
+                describe(&mut info);
+
+                if legacy {
+                    register_legacy(engine, &root, &info)?;
+                } else {
+                    catalog::put(engine, &info)?;
+                }
+            }
+        }
+
+        Ok(Self {
+            codec,
+            version,
+            versioned: !(legacy && read_only),
+            migrations,
+            dynamic,
+            table: name,
+        })
+    }
+
+    /// Encode the data of a record at the current version.
+    pub(crate) fn encode(&self, data: &T) -> DbResult<Vec<u8>> {
+        let mut bytes = self.version.to_be_bytes().to_vec();
+        bytes.extend(self.codec.encode(data)?);
+
+        Ok(bytes)
+    }
+
+    /// Decode the data of a record, upgrading it to the current version if it is older.
+    ///
+    /// # Arguments
+    ///
+    /// * `bytes` - The encoded record, as written by [`Schema::encode`].
+    pub(crate) fn decode(&self, bytes: &[u8]) -> DbResult<T> {
+        match self.split(bytes)? {
+            (version, data) if version == self.version || self.dynamic => self.codec.decode(data),
+            (version, data) => match self.migrations.get(&version) {
+                Some(migration) => migration(data),
+                None => Err(TinyBaseError::MissingMigration {
+                    table: self.table.clone(),
+                    version,
+                }),
+            },
+        }
+    }
+
+    /// Get the version a record was written with.
+    ///
+    /// # Arguments
+    ///
+    /// * `bytes` - The encoded record, as written by [`Schema::encode`].
+    pub(crate) fn record_version(&self, bytes: &[u8]) -> DbResult<u32> {
+        Ok(self.split(bytes)?.0)
+    }
+
+    /// Split an encoded record into its version and its data.
+    fn split<'a>(&self, bytes: &'a [u8]) -> DbResult<(u32, &'a [u8])> {
+        if !self.versioned {
+            return Ok((INITIAL_VERSION, bytes));
+        }
+
+        match bytes.split_first_chunk::<VERSION_LEN>() {
+            Some((version, data)) => Ok((u32::from_be_bytes(*version), data)),
+            None => Err(TinyBaseError::Codec(
+                format!(
+                    "record of table {} is missing its schema version",
+                    self.table
+                )
+                .into(),
+            )),
+        }
+    }
+}
+
+/// Key of the last record converted by [`register_legacy`] in the meta tree.
+fn legacy_key(name: &str) -> String {
+    format!("{}#legacy", name)
+}
+
+/// Record a table written before the catalog existed, prefixing each of its records with the
+/// initial version.
+///
+/// Records are converted in batches, each along with the key of its last record, so a conversion
+/// which is interrupted resumes after the records already converted. The table is only recorded
+/// in the catalog once every record has been converted.
+fn register_legacy(engine: &Db, root: &Tree, info: &TableInfo) -> DbResult<()> {
+    let meta = engine.open_tree(META_TREE)?;
+    let marker = legacy_key(&info.name);
+    let start = match meta.get(&marker)? {
+        Some(key) => Bound::Excluded(key),
+        None => Bound::Unbounded,
+    };
+
+    let mut records = vec![];
+    for entry in root.range((start, Bound::Unbounded)) {
+        let (key, value) = entry?;
+
+        let mut bytes = INITIAL_VERSION.to_be_bytes().to_vec();
+        bytes.extend_from_slice(&value);
+        records.push((key, bytes));
+
+        if records.len() == LEGACY_BATCH_SIZE {
+            convert_legacy(root, &meta, &marker, &records)?;
+            records.clear();
+        }
+    }
+
+    convert_legacy(root, &meta, &marker, &records)?;
+
+    let encoded = encode(info)?;
+    let catalog = engine.open_tree(CATALOG_TREE)?;
+    (&catalog, &meta)
+        .transaction(|(catalog, meta)| {
+            catalog.insert(info.name.as_bytes(), encoded.as_slice())?;
+            meta.remove(marker.as_str())?;
+
+            Ok(())
+        })
+        .map_err(transaction_error)
+}
+
+/// Write a batch of converted records along with the key of the last one.
+fn convert_legacy(
+    root: &Tree,
+    meta: &Tree,
+    marker: &str,
+    records: &[(IVec, Vec<u8>)],
+) -> DbResult<()> {
+    let Some((last, _)) = records.last() else {
+        return Ok(());
+    };
+
+    (root, meta)
+        .transaction(|(root, meta)| {
+            for (key, bytes) in records {
+                root.insert(key, bytes.as_slice())?;
+            }
+            meta.insert(marker, last)?;
+
+            Ok(())
+        })
+        .map_err(transaction_error)
+}
+
+/// Configures and opens a [`Table`].
+///
+/// Records are written with the current schema version of the table. Records written with an
+/// older version are upgraded by the migration registered for that version when they are read,
+/// and are stored at the current version the next time they are written or once
+/// [`Table::migrate`](crate::table::TableInner::migrate) is called.
+pub struct TableBuilder<'a, T: TableType, C: Codec> {
+    db: &'a TinyBase,
+    name: String,
+    codec: Arc<C>,
+    version: u32,
+    fingerprint: Option<String>,
+    migrations: HashMap<u32, Migration<T>>,
+    dynamic: bool,
+}
+
+impl<'a, T: TableType, C: Codec> TableBuilder<'a, T, C> {
+    pub(crate) fn new(db: &'a TinyBase, name: &str, codec: C) -> Self {
+        Self {
+            db,
+            name: name.to_owned(),
+            codec: Arc::new(codec),
+            version: INITIAL_VERSION,
+            fingerprint: None,
+            migrations: HashMap::new(),
+            dynamic: false,
+        }
+    }
+
+    /// Open the table with whichever type and version it was created with, decoding records of
+    /// every version as they are.
+    pub(crate) fn dynamic(mut self) -> Self {
+        self.dynamic = true;
+        self
+    }
+
+    /// Set the current schema version of the table, which defaults to [`INITIAL_VERSION`].
+    ///
+    /// The version can only ever be increased.
+    pub fn version(mut self, version: u32) -> Self {
+        self.version = version;
+        self
+    }
+
+    /// Set a fingerprint of the record type, such as a description of its fields.
+    ///
+    /// Once recorded, the table can't be opened with another fingerprint at the same version, which
+    /// catches types changed without increasing the version. Tables opened without a fingerprint
+    /// are not checked.
+    pub fn fingerprint(mut self, fingerprint: impl Into<String>) -> Self {
+        self.fingerprint = Some(fingerprint.into());
+        self
+    }
+
+    /// Register how records written with an older version are upgraded to the current one.
+    ///
+    /// Migrations aren't chained, each one converts records of its version straight to the
+    /// current type. Once the version is increased again, every registered migration has to
+    /// produce the new type.
+    ///
+    /// # Arguments
+    ///
+    /// * `version` - The version the migration upgrades from.
+    /// * `migration` - Converts the data of the record at that version to the current type.
+    pub fn migration<O: DeserializeOwned>(
+        mut self,
+        version: u32,
+        migration: impl Fn(O) -> T + Send + Sync + 'static,
+    ) -> Self {
+        let codec = self.codec.clone();
+        self.migrations.insert(
+            version,
+            Box::new(move |bytes| Ok(migration(Codec::decode(codec.as_ref(), bytes)?))),
+        );
+        self
+    }
+
+    /// Open the table.
+    ///
+    /// # Returns
+    ///
+    /// The opened [`Table`], or an error if the table was created with another codec, already
+    /// has a newer version or has another fingerprint.
+    pub fn open(self) -> DbResult<Table<T>> {
+        let (db, name) = (self.db, self.name.clone());
+
+        // Tables can't be dropped or renamed while they are being opened.
+        let mut handles = db.open_tables.lock();
+        let shared = handles.get(&name).and_then(Weak::upgrade);
+
+        let commit = db.commits.read().unwrap();
+        let schema = Schema::open(self, shared.is_none())?;
+        drop(commit);
+
+        let shared = match shared {
+            Some(shared) => shared,
+            None => {
+                let shared = Arc::new(SharedTable::new(&db.engine, &name)?);
+                handles.insert(name.clone(), Arc::downgrade(&shared));
+                shared
+            }
+        };
+
+        Ok(Table(Arc::new(TableInner::new(
+            &db.engine,
+            &name,
+            db.read_only,
+            db.group_commit.clone(),
+            db.commits.clone(),
+            shared,
+            schema,
+        )?)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::{Deserialize, Serialize};
+
+    use super::*;
+
+    #[derive(Serialize, Deserialize, Debug, Clone)]
+    struct PersonV1 {
+        name: String,
+    }
+
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+    struct Person {
+        name: String,
+        age: u32,
+    }
+
+    fn upgrade(old: PersonV1) -> Person {
+        Person {
+            name: old.name,
+            age: 18,
+        }
+    }
+
+    #[test]
+    fn schema_migration() {
+        let db = TinyBase::new(None, true);
+
+        let v1: Table<PersonV1> = db.open_table("people").unwrap();
+        let old = v1
+            .insert(PersonV1 {
+                name: "John".to_string(),
+            })
+            .unwrap();
+        drop(v1);
+
+        // Old records can't be read as the new type without a migration.
+        let table: Table<Person> = db
+            .table_builder("people", Bincode)
+            .version(2)
+            .open()
+            .unwrap();
+        assert!(matches!(
+            table.select(old),
+            Err(TinyBaseError::MissingMigration { version: 1, .. })
+        ));
+        drop(table);
+
+        let table: Table<Person> = db
+            .table_builder("people", Bincode)
+            .version(2)
+            .migration(1, upgrade)
+            .open()
+            .unwrap();
+        let age = table.create_index("age", |person| person.age).unwrap();
+
+        // Old records are upgraded when read, including when building indexes.
+        assert_eq!(table.select(old).unwrap().unwrap().data.age, 18);
+        assert_eq!(age.select(&18).unwrap()[0].id, old);
+
+        let new = table
+            .insert(Person {
+                name: "Jane".to_string(),
+                age: 30,
+            })
+            .unwrap();
+
+        assert_eq!(table.migrate().unwrap(), 1);
+        assert_eq!(table.migrate().unwrap(), 0);
+        assert_eq!(age.select(&18).unwrap()[0].id, old);
+        drop((age, table));
+
+        // Every record is at the current version, so no migration is needed anymore.
+        let table: Table<Person> = db
+            .table_builder("people", Bincode)
+            .version(2)
+            .open()
+            .unwrap();
+        assert_eq!(table.select(old).unwrap().unwrap().data.name, "John");
+        assert_eq!(table.select(new).unwrap().unwrap().data.age, 30);
+
+        assert!(matches!(
+            db.open_table::<Person>("people"),
+            Err(TinyBaseError::SchemaVersion {
+                stored: 2,
+                requested: 1,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn schema_legacy_resume() {
+        let db = TinyBase::new(None, true);
+        let raw = db.engine.open_tree("legacy").unwrap();
+        for id in 1..=3u64 {
+            raw.insert(
+                encode(&id).unwrap(),
+                encode(&format!("value{}", id)).unwrap(),
+            )
+            .unwrap();
+        }
+
+        // Interrupted once the first record was converted.
+        let mut converted = INITIAL_VERSION.to_be_bytes().to_vec();
+        converted.extend(encode(&"value1").unwrap());
+        raw.insert(encode(&1u64).unwrap(), converted).unwrap();
+        let meta = db.engine.open_tree(META_TREE).unwrap();
+        meta.insert(legacy_key("legacy"), encode(&1u64).unwrap())
+            .unwrap();
+
+        let table: Table<String> = db.open_table("legacy").unwrap();
+        for id in 1..=3 {
+            assert_eq!(
+                table.select(id).unwrap().unwrap().data,
+                format!("value{}", id)
+            );
+        }
+        assert!(!meta.contains_key(legacy_key("legacy")).unwrap());
+    }
+
+    #[test]
+    fn schema_migration_on_write() {
+        let db = TinyBase::new(None, true);
+
+        let v1: Table<PersonV1> = db.open_table("people").unwrap();
+        let id = v1
+            .insert(PersonV1 {
+                name: "John".to_string(),
+            })
+            .unwrap();
+        drop(v1);
+
+        let table: Table<Person> = db
+            .table_builder("people", Bincode)
+            .version(2)
+            .migration(1, upgrade)
+            .open()
+            .unwrap();
+
+        // Updating an old record stores it at the current version, even if its data is unchanged.
+        let updated = table.update(&[id], |person| person).unwrap();
+        assert_eq!(updated[0].data.age, 18);
+        assert_eq!(table.migrate().unwrap(), 0);
+    }
+}
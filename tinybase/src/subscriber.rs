@@ -1,28 +1,308 @@
-use std::sync::mpsc::Receiver;
+use std::collections::HashMap;
+use std::sync::mpsc::{Receiver, RecvTimeoutError, SyncSender, TryRecvError};
+use std::sync::{Arc, RwLock, Weak};
+use std::time::Duration;
 
-use crate::{table::SenderMap, Record};
+use crate::query_builder::QueryCondition;
+use crate::result::DbResult;
+use crate::table::{SenderMap, TableType};
+use crate::Record;
 
-#[derive(Clone)]
-pub(crate) enum Event<T> {
+/// Number of events buffered for each subscriber.
+/// Subscribers which fall further behind are disconnected rather than slowing down writers.
+pub(crate) const SUBSCRIBER_CAPACITY: usize = 1024;
+
+/// A change made to a table.
+#[derive(Debug, Clone)]
+pub enum Event<T> {
+    /// A record was removed, holding its data before it was removed.
     Remove(Record<T>),
+    /// A record was inserted.
     Insert(Record<T>),
+    /// A record was updated from `old_data` to `new_data`.
     Update { id: u64, old_data: T, new_data: T },
 }
 
-pub(crate) struct Subscriber<T> {
+impl<T> Event<T> {
+    /// ID of the record which was changed.
+    pub fn id(&self) -> u64 {
+        match self {
+            Event::Remove(record) | Event::Insert(record) => record.id,
+            Event::Update { id, .. } => *id,
+        }
+    }
+}
+
+impl<T: TableType> Event<T> {
+    /// Check if the old or new data of the event matches a condition.
+    pub(crate) fn matches(&self, condition: &QueryCondition<T>) -> DbResult<bool> {
+        Ok(match self {
+            Event::Remove(record) | Event::Insert(record) => condition.matches(&record.data)?,
+            Event::Update {
+                old_data, new_data, ..
+            } => condition.matches(old_data)? || condition.matches(new_data)?,
+        })
+    }
+}
+
+/// Sending half of a subscription, held by the table.
+pub(crate) enum EventSender<T> {
+    /// Feeds a [`Subscriber`].
+    Blocking(SyncSender<Event<T>>),
+    /// Feeds the stream of a subscriber, see `Subscriber::into_stream`.
+    #[cfg(feature = "async")]
+    Stream(tokio::sync::mpsc::Sender<Event<T>>),
+}
+
+impl<T> EventSender<T> {
+    /// Send an event without waiting.
+    ///
+    /// # Returns
+    ///
+    /// Whether the event was sent, which fails if the subscriber is gone or has no room left.
+    pub(crate) fn try_send(&self, event: Event<T>) -> bool {
+        match self {
+            Self::Blocking(sender) => sender.try_send(event).is_ok(),
+            #[cfg(feature = "async")]
+            Self::Stream(sender) => sender.try_send(event).is_ok(),
+        }
+    }
+}
+
+/// Receives the changes made to a table once they have been committed.
+///
+/// Events are buffered until they are received, up to 1024 events. A subscriber which falls
+/// further behind is disconnected, so it only yields the events already buffered, as if the table
+/// had been dropped. Iterating over a subscriber blocks until the next event, and ends once it is
+/// closed.
+pub struct Subscriber<T: TableType + 'static> {
     id: u64,
-    pub rx: Receiver<Event<T>>,
-    senders: SenderMap<Event<T>>,
+    rx: Receiver<Event<T>>,
+    /// Weak so that the sender is dropped along with the table, which ends the subscription.
+    senders: Weak<RwLock<HashMap<u64, EventSender<T>>>>,
+    /// Only events where the old or new data matches this condition are delivered.
+    condition: Option<QueryCondition<T>>,
 }
 
-impl<T> Subscriber<T> {
-    pub fn new(id: u64, rx: Receiver<Event<T>>, senders: SenderMap<Event<T>>) -> Self {
-        Self { id, rx, senders }
+impl<T: TableType> Subscriber<T> {
+    pub(crate) fn new(
+        id: u64,
+        rx: Receiver<Event<T>>,
+        senders: &SenderMap<T>,
+        condition: Option<QueryCondition<T>>,
+    ) -> Self {
+        Self {
+            id,
+            rx,
+            senders: Arc::downgrade(senders),
+            condition,
+        }
+    }
+
+    /// Block until the next event is received.
+    ///
+    /// # Returns
+    ///
+    /// The next [`Event`], or [`None`] if the subscriber is closed.
+    pub fn recv(&self) -> DbResult<Option<Event<T>>> {
+        while let Ok(event) = self.rx.recv() {
+            if self.matches(&event)? {
+                return Ok(Some(event));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Receive the next event without blocking.
+    ///
+    /// # Returns
+    ///
+    /// The next [`Event`], or [`None`] if no event is pending.
+    pub fn try_recv(&self) -> DbResult<Option<Event<T>>> {
+        loop {
+            match self.rx.try_recv() {
+                Ok(event) if self.matches(&event)? => return Ok(Some(event)),
+                Ok(_) => continue,
+                Err(TryRecvError::Empty | TryRecvError::Disconnected) => return Ok(None),
+            }
+        }
+    }
+
+    /// Block until the next event is received or the timeout elapses.
+    ///
+    /// # Arguments
+    ///
+    /// * `timeout` - The maximum time to wait for an event.
+    ///
+    /// # Returns
+    ///
+    /// The next [`Event`], or [`None`] if no event was received in time.
+    pub fn recv_timeout(&self, timeout: Duration) -> DbResult<Option<Event<T>>> {
+        loop {
+            match self.rx.recv_timeout(timeout) {
+                Ok(event) if self.matches(&event)? => return Ok(Some(event)),
+                Ok(_) => continue,
+                Err(RecvTimeoutError::Timeout | RecvTimeoutError::Disconnected) => return Ok(None),
+            }
+        }
+    }
+
+    /// Check if the table has been dropped or the subscriber has been disconnected for falling
+    /// behind, in which case no new events will be sent.
+    pub fn is_closed(&self) -> bool {
+        self.senders
+            .upgrade()
+            .is_none_or(|senders| !senders.read().unwrap().contains_key(&self.id))
+    }
+
+    /// Move the subscription over to a channel which can be awaited.
+    ///
+    /// # Returns
+    ///
+    /// The receiver of the channel, starting with the events which were still buffered, along with
+    /// the condition of the subscriber.
+    #[cfg(feature = "async")]
+    pub(crate) fn into_async(
+        mut self,
+    ) -> (
+        tokio::sync::mpsc::Receiver<Event<T>>,
+        Option<QueryCondition<T>>,
+    ) {
+        let (tx, rx) = tokio::sync::mpsc::channel(SUBSCRIBER_CAPACITY);
+
+        // Nothing is dispatched while the senders are locked, so no event is missed or reordered.
+        // Taking them also keeps the subscriber from removing the new sender once dropped.
+        let senders = std::mem::take(&mut self.senders).upgrade();
+        let mut senders = senders.as_ref().map(|senders| senders.write().unwrap());
+
+        // At most as many events as the channel holds are buffered.
+        while let Ok(event) = self.rx.try_recv() {
+            let _ = tx.try_send(event);
+        }
+
+        if let Some(senders) = &mut senders {
+            if senders.contains_key(&self.id) {
+                senders.insert(self.id, EventSender::Stream(tx));
+            }
+        }
+
+        (rx, self.condition.take())
+    }
+
+    /// Check if the old or new data of an event matches the condition.
+    fn matches(&self, event: &Event<T>) -> DbResult<bool> {
+        match &self.condition {
+            Some(condition) => event.matches(condition),
+            None => Ok(true),
+        }
     }
 }
 
-impl<T> Drop for Subscriber<T> {
+impl<T: TableType> Iterator for Subscriber<T> {
+    type Item = DbResult<Event<T>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.recv().transpose()
+    }
+}
+
+impl<T: TableType> Drop for Subscriber<T> {
     fn drop(&mut self) {
-        self.senders.write().unwrap().remove(&self.id);
+        if let Some(senders) = self.senders.upgrade() {
+            senders.write().unwrap().remove(&self.id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ConditionBuilder, Table, TinyBase};
+
+    #[test]
+    fn subscribe() {
+        let db = TinyBase::new(None, true);
+        let table: Table<String> = db.open_table("test_table").unwrap();
+        let subscriber = table.subscribe().unwrap();
+
+        let id = table.insert("value1".to_string()).unwrap();
+        table.update(&[id], |_| "value2".to_string()).unwrap();
+        table.delete(id).unwrap();
+
+        assert!(matches!(
+            subscriber.try_recv().unwrap(),
+            Some(Event::Insert(record)) if record.id == id && record.data == "value1"
+        ));
+        assert!(matches!(
+            subscriber.try_recv().unwrap(),
+            Some(Event::Update { old_data, new_data, .. })
+                if old_data == "value1" && new_data == "value2"
+        ));
+        assert!(matches!(
+            subscriber.try_recv().unwrap(),
+            Some(Event::Remove(record)) if record.data == "value2"
+        ));
+        assert!(subscriber.try_recv().unwrap().is_none());
+
+        // Iteration ends once the table is gone.
+        drop(table);
+        assert!(subscriber.recv().unwrap().is_none());
+    }
+
+    #[test]
+    fn subscribe_filtered() {
+        let db = TinyBase::new(None, true);
+        let table: Table<String> = db.open_table("test_table").unwrap();
+        let length = table.create_index("length", |value| value.len()).unwrap();
+
+        let subscriber = table
+            .subscribe_filtered(ConditionBuilder::or(
+                ConditionBuilder::by(&length, 1),
+                ConditionBuilder::filter(|value: &String| value.starts_with('b')),
+            ))
+            .unwrap();
+
+        let short = table.insert("a".to_string()).unwrap();
+        table.insert("aa".to_string()).unwrap();
+        table.insert("bb".to_string()).unwrap();
+
+        // Updates are delivered when either side matches, so records leaving the set are seen.
+        table.update(&[short], |_| "aaa".to_string()).unwrap();
+
+        let ids: Vec<u64> = std::iter::from_fn(|| subscriber.try_recv().unwrap())
+            .map(|event| event.id())
+            .collect();
+
+        assert_eq!(ids.len(), 3);
+        assert_eq!(ids[0], short);
+        assert_eq!(ids[2], short);
+    }
+
+    #[test]
+    fn subscribe_behind() {
+        let db = TinyBase::new(None, true);
+        let table: Table<usize> = db.open_table("test_table").unwrap();
+        let subscriber = table.subscribe().unwrap();
+
+        // Writers don't wait for a subscriber without room for more events, it is disconnected.
+        for value in 0..=SUBSCRIBER_CAPACITY {
+            table.insert(value).unwrap();
+        }
+
+        assert!(subscriber.is_closed());
+        assert_eq!(subscriber.count(), SUBSCRIBER_CAPACITY);
+    }
+
+    #[test]
+    fn subscribe_from_thread() {
+        let db = TinyBase::new(None, true);
+        let table: Table<String> = db.open_table("test_table").unwrap();
+        let subscriber = table.subscribe().unwrap();
+
+        let receiver = std::thread::spawn(move || subscriber.recv().unwrap().map(|e| e.id()));
+        let id = table.insert("value1".to_string()).unwrap();
+
+        assert_eq!(receiver.join().unwrap(), Some(id));
     }
 }
@@ -1,24 +1,110 @@
-use std::collections::HashMap;
+use std::any;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fmt::Debug;
-use std::ops::Deref;
-use std::sync::mpsc::{self, Sender};
-use std::sync::{Arc, RwLock};
+use std::ops::{Bound, Deref};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::sync::{Arc, RwLock, RwLockWriteGuard, Weak};
 
 use serde::de::DeserializeOwned;
 use serde::Serialize;
-use sled::{Db, Tree};
+use sled::{Db, IVec, Tree};
 
+use crate::batch::{self, Batch};
+use crate::catalog::{self, ConstraintInfo, IndexInfo};
 use crate::constraint::{Constraint, ConstraintInner};
+use crate::durability::GroupCommit;
 use crate::encoding::{decode, encode};
-use crate::index::{Index, IndexInner, IndexType};
+use crate::index::{Index, IndexInner, IndexType, IndexWriter};
+use crate::query_builder::QueryCondition;
 use crate::record::Record;
-use crate::result::DbResult;
-use crate::subscriber::{Event, Subscriber};
+use crate::result::{DbResult, TinyBaseError};
+use crate::schema::Schema;
+use crate::subscriber::{Event, EventSender, Subscriber, SUBSCRIBER_CAPACITY};
 
-pub(crate) type SenderMap<T> = Arc<RwLock<HashMap<u64, Sender<T>>>>;
+pub(crate) type SenderMap<T> = Arc<RwLock<HashMap<u64, EventSender<T>>>>;
 
-pub trait TableType: Serialize + DeserializeOwned + Clone + Debug {}
-impl<T: Serialize + DeserializeOwned + Debug + Clone> TableType for T {}
+/// Tree holding internal metadata, such as the state of persisted indexes.
+pub(crate) const META_TREE: &str = "__tinybase_meta";
+
+/// Maximum number of entries kept in the change log of a table.
+/// Indexes which fall further behind than this are rebuilt when opened.
+const CHANGE_LOG_CAPACITY: usize = 10_000;
+
+/// Number of records rewritten at once by [`TableInner::migrate`].
+const MIGRATE_BATCH_SIZE: usize = 1000;
+
+/// Type of the records of a table.
+///
+/// Records must be `Send + Sync` whether or not the `async` feature is enabled, so that tables,
+/// queries and subscribers can be used from other threads.
+pub trait TableType: Serialize + DeserializeOwned + Clone + Debug + Send + Sync {}
+impl<T: Serialize + DeserializeOwned + Debug + Clone + Send + Sync> TableType for T {}
+
+/// Key of the next record ID in the meta tree.
+const NEXT_ID_KEY: &str = "#next_id";
+
+/// Get the next record ID of the database without generating it.
+///
+/// Databases written before the next ID was stored continue from the ID generator of sled, which
+/// generated their IDs.
+pub(crate) fn next_id(engine: &Db, meta: &Tree) -> DbResult<u64> {
+    match meta.get(NEXT_ID_KEY)? {
+        Some(bytes) => decode(&bytes),
+        None => Ok(engine.generate_id()?),
+    }
+}
+
+/// Change the next record ID, which is retried if it is changed concurrently.
+///
+/// # Arguments
+///
+/// * `f` - Computes the new next ID from the current one, [`None`] leaves it unchanged.
+///
+/// # Returns
+///
+/// The next ID before it was changed.
+fn update_next_id(
+    engine: &Db,
+    meta: &Tree,
+    f: impl Fn(u64) -> DbResult<Option<u64>>,
+) -> DbResult<u64> {
+    loop {
+        let current = meta.get(NEXT_ID_KEY)?;
+        let id = match &current {
+            Some(bytes) => decode(bytes)?,
+            None => engine.generate_id()?,
+        };
+
+        let Some(next) = f(id)? else {
+            return Ok(id);
+        };
+
+        if meta
+            .compare_and_swap(NEXT_ID_KEY, current, Some(encode(&next)?))?
+            .is_ok()
+        {
+            return Ok(id);
+        }
+    }
+}
+
+/// Generate a record ID, which is never generated again.
+pub(crate) fn generate_id(engine: &Db, meta: &Tree) -> DbResult<u64> {
+    update_next_id(engine, meta, |id| {
+        id.checked_add(1)
+            .map(Some)
+            .ok_or(TinyBaseError::IdOutOfRange(id))
+    })
+}
+
+/// Make sure record IDs are only generated from `next_id` onward, such as after records were
+/// written with IDs from elsewhere.
+pub(crate) fn reserve_ids(engine: &Db, meta: &Tree, next_id: u64) -> DbResult<()> {
+    update_next_id(engine, meta, |id| Ok((id < next_id).then_some(next_id)))?;
+
+    Ok(())
+}
 
 /// Provides methods for interacting with a typed table.
 pub struct Table<T: TableType + 'static>(pub(crate) Arc<TableInner<T>>);
@@ -26,6 +112,9 @@ pub struct Table<T: TableType + 'static>(pub(crate) Arc<TableInner<T>>);
 impl<T: TableType + 'static> Table<T> {
     /// Create an index on the table.
     ///
+    /// The index is persisted, so opening it again only applies the writes made since it was
+    /// last used. See [`Table::create_index_versioned`] for when the key function changes.
+    ///
     /// # Arguments
     ///
     /// * `name` - The name of the index.
@@ -34,26 +123,95 @@ impl<T: TableType + 'static> Table<T> {
     /// # Returns
     ///
     /// An [`Index`] instance for the created index.
-    pub fn create_index<I: IndexType>(
+    pub fn create_index<I: IndexType + 'static>(
         &self,
         name: &str,
         key_func: impl Fn(&T) -> I + Send + Sync + 'static,
     ) -> DbResult<Index<T, I>> {
-        let sender_id = self.engine.generate_id()?;
-        let (tx, rx) = mpsc::channel();
-
-        let subscriber = Subscriber::new(sender_id, rx, self.senders.clone());
-        self.senders.write().unwrap().insert(sender_id, tx);
+        self.create_index_versioned(name, 0, key_func)
+    }
 
+    /// Create an index on the table with an explicit version of its key function.
+    ///
+    /// The persisted index is rebuilt from scratch whenever the version differs from the one it
+    /// was built with, so bump the version whenever `key_func` or the key type changes.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the index.
+    /// * `version` - The version of the key function.
+    /// * `key_func` - A function which computes the index key for each record.
+    ///
+    /// # Returns
+    ///
+    /// An [`Index`] instance for the created index.
+    pub fn create_index_versioned<I: IndexType + 'static>(
+        &self,
+        name: &str,
+        version: u32,
+        key_func: impl Fn(&T) -> I + Send + Sync + 'static,
+    ) -> DbResult<Index<T, I>> {
         let weak_self = Arc::downgrade(&self.0);
 
-        Ok(Index(Arc::new(IndexInner::new(
-            &format!("{}_idx_{}", self.name, name),
+        let index = IndexInner::new(
+            &catalog::index_tree(&self.name, name),
             &self.engine,
             weak_self,
+            version,
             key_func,
-            subscriber,
-        )?)))
+        )?;
+
+        if !self.read_only {
+            let _commit = self.commits.read().unwrap();
+            catalog::update(&self.engine, &self.name, |info| {
+                info.indexes.retain(|index| index.name != name);
+                info.indexes.push(IndexInfo {
+                    name: name.to_owned(),
+                    key_type: any::type_name::<I>().to_owned(),
+                    version,
+                });
+            })?;
+        }
+
+        Ok(Index(index))
+    }
+
+    /// Subscribe to every change made to the table.
+    ///
+    /// # Returns
+    ///
+    /// A [`Subscriber`] receiving an [`Event`] for each committed insert, update and removal.
+    pub fn subscribe(&self) -> DbResult<Subscriber<T>> {
+        self.subscriber(None)
+    }
+
+    /// Subscribe to the changes made to records matching a condition.
+    ///
+    /// # Arguments
+    ///
+    /// * `condition` - The condition which the old or new data of a record must match.
+    ///
+    /// # Returns
+    ///
+    /// A [`Subscriber`] receiving an [`Event`] for each matching change.
+    pub fn subscribe_filtered(
+        &self,
+        condition: impl Into<QueryCondition<T>>,
+    ) -> DbResult<Subscriber<T>> {
+        self.subscriber(Some(condition.into()))
+    }
+
+    /// Register a new subscriber with an optional condition.
+    fn subscriber(&self, condition: Option<QueryCondition<T>>) -> DbResult<Subscriber<T>> {
+        let sender_id = self.engine.generate_id()?;
+        let (tx, rx) = mpsc::sync_channel(SUBSCRIBER_CAPACITY);
+
+        self.senders
+            .write()
+            .unwrap()
+            .insert(sender_id, EventSender::Blocking(tx));
+
+        Ok(Subscriber::new(sender_id, rx, &self.senders, condition))
     }
 }
 
@@ -71,15 +229,49 @@ impl<T: TableType> Deref for Table<T> {
     }
 }
 
+/// State of a table shared by every handle opened on it, kept by [`catalog::OpenTables`].
+pub(crate) struct SharedTable {
+    /// This has a global lock to make sure that constraints are honored during inserts.
+    /// Writers take the write lock even when they only read the tree through it.
+    pub(crate) root: RwLock<Tree>,
+    /// Number of entries in the change log.
+    log_len: AtomicUsize,
+    /// Open indexes keyed by name, which are written along with every write to the table.
+    /// Handles of an index are kept in the order they were opened.
+    indexes: RwLock<HashMap<String, Vec<Weak<dyn IndexWriter>>>>,
+}
+
+impl SharedTable {
+    /// Open the shared state of a table which has no open handles.
+    pub(crate) fn new(engine: &Db, name: &str) -> DbResult<Self> {
+        Ok(Self {
+            root: RwLock::new(engine.open_tree(name)?),
+            log_len: AtomicUsize::new(engine.open_tree(format!("{}#log", name))?.len()),
+            indexes: RwLock::new(HashMap::new()),
+        })
+    }
+}
+
 pub struct TableInner<T>
 where
     T: TableType + 'static,
 {
     pub(crate) engine: Db,
-    /// This has a global lock to make sure that constraints are honored during inserts.
-    pub(crate) root: RwLock<Tree>,
-    name: String,
-    senders: SenderMap<Event<T>>,
+    /// State shared by every handle of the table.
+    pub(crate) shared: Arc<SharedTable>,
+    pub(crate) name: String,
+    /// Rejects every write when set.
+    read_only: bool,
+    /// Persists durable writes, shared by every table of the database.
+    group_commit: Arc<GroupCommit>,
+    /// Held while writing to any tree, so a backup never observes a write partially.
+    pub(crate) commits: Arc<RwLock<()>>,
+    /// Serializes the records of the table and upgrades those of older schema versions.
+    pub(crate) schema: Arc<Schema<T>>,
+    /// Change log mapping a sequence number, counted per table, to the ID of the record written.
+    log: Tree,
+    meta: Tree,
+    senders: SenderMap<T>,
     constraints: RwLock<Vec<Constraint<T>>>,
 }
 
@@ -96,13 +288,30 @@ where
     ///
     /// * `engine` - The database engine.
     /// * `name` - The name of the table.
-    pub(crate) fn new(engine: &Db, name: &str) -> DbResult<Self> {
-        let root = RwLock::new(engine.open_tree(name)?);
-
+    /// * `read_only` - Whether writes to the table are rejected.
+    /// * `group_commit` - Flushes the database for durable writes.
+    /// * `commits` - Lock shared by every commit to the database.
+    /// * `shared` - State shared with the other handles of the table.
+    /// * `schema` - Serializes the records of the table.
+    pub(crate) fn new(
+        engine: &Db,
+        name: &str,
+        read_only: bool,
+        group_commit: Arc<GroupCommit>,
+        commits: Arc<RwLock<()>>,
+        shared: Arc<SharedTable>,
+        schema: Schema<T>,
+    ) -> DbResult<Self> {
         Ok(Self {
             engine: engine.clone(),
-            root,
+            shared,
             name: name.to_owned(),
+            read_only,
+            group_commit,
+            commits,
+            schema: Arc::new(schema),
+            log: engine.open_tree(format!("{}#log", name))?,
+            meta: engine.open_tree(META_TREE)?,
             senders: Arc::new(RwLock::new(HashMap::new())),
             constraints: RwLock::new(Vec::new()),
         })
@@ -118,19 +327,154 @@ where
     ///
     /// The ID of the new record.
     pub fn insert(&self, value: T) -> DbResult<u64> {
-        let root = self.root.write().unwrap();
+        let root = self.locked();
 
-        let record = Record {
-            id: self.engine.generate_id()?,
-            data: value.clone(),
+        let id = self.generate_id()?;
+        self.insert_record(&root, Record { id, data: value })?;
+
+        Ok(id)
+    }
+
+    /// Insert a record keeping its ID, which is never generated again afterwards.
+    ///
+    /// # Errors
+    ///
+    /// [`TinyBaseError::IdExists`] if a record with the same ID is already in the table, or
+    /// [`TinyBaseError::IdOutOfRange`] if the ID is the largest one, which leaves none to generate.
+    pub(crate) fn insert_with_id(&self, record: Record<T>) -> DbResult<()> {
+        let root = self.locked();
+
+        let next_id = record
+            .id
+            .checked_add(1)
+            .ok_or(TinyBaseError::IdOutOfRange(record.id))?;
+        if root.contains_key(encode(&record.id)?)? {
+            return Err(TinyBaseError::IdExists(record.id));
+        }
+
+        // IDs are only reserved for records which pass the constraints and are written.
+        self.check_constraint(&root, &record, &vec![])?;
+        reserve_ids(&self.engine, &self.meta, next_id)?;
+        self.write(&root, &[(record.id, Some(&record.data))])?;
+
+        self.dispatch_event(Event::Insert(record));
+
+        Ok(())
+    }
+
+    /// Write a record over the one found while holding the write lock, or as a new record if none
+    /// is found, so the replaced record can't be removed concurrently.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - The new data of the record.
+    /// * `find` - Finds the ID of the record to replace.
+    ///
+    /// # Returns
+    ///
+    /// The replaced record, or [`None`] if the value was inserted as a new record.
+    pub(crate) fn replace(
+        &self,
+        value: T,
+        find: impl FnOnce(&T) -> DbResult<Option<u64>>,
+    ) -> DbResult<Option<Record<T>>> {
+        let root = self.locked();
+
+        let old = match find(&value)? {
+            Some(id) => self.tree_select(&root, id)?,
+            None => None,
         };
 
+        let Some(old) = old else {
+            let id = self.generate_id()?;
+            self.insert_record(&root, Record { id, data: value })?;
+            return Ok(None);
+        };
+
+        let record = Record {
+            id: old.id,
+            data: value,
+        };
         self.check_constraint(&root, &record, &vec![])?;
-        root.insert(encode(&record.id)?, encode(&value)?)?;
+        self.write(&root, &[(record.id, Some(&record.data))])?;
 
-        self.dispatch_event(Event::Insert(record.clone()));
+        self.dispatch_event(Event::Update {
+            id: record.id,
+            old_data: old.data.clone(),
+            new_data: record.data,
+        });
 
-        Ok(record.id)
+        Ok(Some(old))
+    }
+
+    /// Check the constraints, write and dispatch the event of a new record.
+    /// Any time you pass the tree it should be obtained via a write lock.
+    fn insert_record(&self, root: &Tree, record: Record<T>) -> DbResult<()> {
+        self.check_constraint(root, &record, &vec![])?;
+        self.write(root, &[(record.id, Some(&record.data))])?;
+
+        self.dispatch_event(Event::Insert(record));
+
+        Ok(())
+    }
+
+    /// Insert a new record into the table, returning only once it has been persisted to disk.
+    ///
+    /// Concurrent durable writes share a single flush, see [`crate::TinyBaseBuilder::group_commit`].
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - The value to insert.
+    ///
+    /// # Returns
+    ///
+    /// The ID of the new record.
+    pub fn insert_durable(&self, value: T) -> DbResult<u64> {
+        let id = self.insert(value)?;
+        self.group_commit.sync()?;
+
+        Ok(id)
+    }
+
+    /// Upgrade every record written with an older schema version to the current version.
+    ///
+    /// Records are rewritten in batches along with the indexes of the table, so indexes are up to
+    /// date with the upgraded records once this returns. No events are dispatched.
+    ///
+    /// # Returns
+    ///
+    /// The number of records upgraded.
+    pub fn migrate(&self) -> DbResult<usize> {
+        let root = self.locked();
+
+        let mut count = 0;
+        let mut records = vec![];
+        for entry in root.iter() {
+            let (key, value) = entry?;
+            if self.schema.record_version(&value)? == self.schema.version {
+                continue;
+            }
+
+            records.push((decode::<u64>(&key)?, self.schema.decode(&value)?));
+
+            if records.len() == MIGRATE_BATCH_SIZE {
+                count += self.migrate_batch(&root, &records)?;
+                records.clear();
+            }
+        }
+
+        count += self.migrate_batch(&root, &records)?;
+
+        Ok(count)
+    }
+
+    /// Rewrite upgraded records at the current schema version.
+    fn migrate_batch(&self, root: &Tree, records: &[(u64, T)]) -> DbResult<usize> {
+        let writes: Vec<(u64, Option<&T>)> =
+            records.iter().map(|(id, data)| (*id, Some(data))).collect();
+        self.write(root, &writes)?;
+
+        Ok(records.len())
     }
 
     /// Check if constraint is met.
@@ -156,7 +500,7 @@ where
 
                     let mut matches = vec![];
                     for additional in additional_items {
-                        let key = index.gen_key(&additional)?;
+                        let key = index.gen_key(additional)?;
                         if matches.contains(&key) {
                             return Err(crate::result::TinyBaseError::BatchOperationConstraints);
                         }
@@ -175,6 +519,51 @@ where
         Ok(())
     }
 
+    /// Check if constraints are met by a batch of writes applied on top of the tree.
+    /// Records which are rewritten or removed by the batch don't conflict through their old values.
+    /// Any time you pass the tree it should probably be obtained via a write lock.
+    pub(crate) fn check_batch(
+        &self,
+        tree: &Tree,
+        writes: &BTreeMap<u64, Option<T>>,
+    ) -> DbResult<()> {
+        for constraint in self.constraints.read().unwrap().iter() {
+            match &constraint.0 {
+                ConstraintInner::Unique(index) => {
+                    let mut keys = HashSet::new();
+
+                    for (id, data) in writes {
+                        let Some(data) = data else { continue };
+
+                        let record = Record {
+                            id: *id,
+                            data: data.clone(),
+                        };
+
+                        let conflict = index
+                            .tree_exists(tree, &record)?
+                            .into_iter()
+                            .any(|other| other != *id && !writes.contains_key(&other));
+
+                        if conflict || !keys.insert(index.gen_key(data)?) {
+                            return Err(crate::result::TinyBaseError::Exists {
+                                constraint: index.idx_name(),
+                                id: *id,
+                            });
+                        }
+                    }
+                }
+                ConstraintInner::Check(condition) => {
+                    if !writes.values().flatten().all(condition) {
+                        return Err(crate::result::TinyBaseError::Condition);
+                    }
+                }
+            };
+        }
+
+        Ok(())
+    }
+
     /// Select a record by its ID.
     ///
     /// # Arguments
@@ -185,21 +574,82 @@ where
     ///
     /// An [`Option`] containing the selected record if it exists, or [`None`] otherwise.
     pub fn select(&self, id: u64) -> DbResult<Option<Record<T>>> {
-        self.tree_select(&self.root.read().unwrap(), id)
+        self.tree_select(&self.shared.root.read().unwrap(), id)
     }
 
     /// Select that doesn't obtain a read lock.
     pub(crate) fn tree_select(&self, tree: &Tree, id: u64) -> DbResult<Option<Record<T>>> {
-        if let Some(serialized) = tree.get(encode(&id)?)? {
+        let key = encode(&id)?;
+        if let Some(serialized) = tree.get(&key)? {
             Ok(Some(Record {
                 id,
-                data: decode(&serialized)?,
+                data: self.schema.decode(&serialized)?,
             }))
         } else {
             Ok(None)
         }
     }
 
+    /// Iterate lazily over every record in the table, ordered by ID.
+    ///
+    /// # Returns
+    ///
+    /// A [`TableIter`] yielding each [`Record`] in the table.
+    pub fn iter(&self) -> TableIter<T> {
+        TableIter {
+            inner: self.shared.root.read().unwrap().iter(),
+            schema: self.schema.clone(),
+        }
+    }
+
+    /// Iterate lazily over the records with an ID greater than `id`, ordered by ID.
+    pub(crate) fn iter_after(&self, id: u64) -> DbResult<TableIter<T>> {
+        Ok(TableIter {
+            inner: self
+                .shared
+                .root
+                .read()
+                .unwrap()
+                .range((Bound::Excluded(encode(&id)?), Bound::Unbounded)),
+            schema: self.schema.clone(),
+        })
+    }
+
+    /// Select every record in the table.
+    ///
+    /// # Returns
+    ///
+    /// All [`Record`] instances in the table, ordered by ID.
+    pub fn scan(&self) -> DbResult<Vec<Record<T>>> {
+        self.iter().collect()
+    }
+
+    /// Get the IDs of every record in the table.
+    ///
+    /// # Returns
+    ///
+    /// All record IDs in the table, in ascending order.
+    pub fn ids(&self) -> DbResult<Vec<u64>> {
+        let mut ids = vec![];
+        for key in self.shared.root.read().unwrap().iter().keys() {
+            ids.push(decode(&key?)?);
+        }
+
+        Ok(ids)
+    }
+
+    /// Get the number of records in the table.
+    ///
+    /// This walks the entire table, so it is linear in the number of records.
+    pub fn len(&self) -> usize {
+        self.shared.root.read().unwrap().len()
+    }
+
+    /// Check if the table contains no records.
+    pub fn is_empty(&self) -> bool {
+        self.shared.root.read().unwrap().is_empty()
+    }
+
     /// Delete a record by its ID.
     ///
     /// # Arguments
@@ -210,15 +660,11 @@ where
     ///
     /// An [`Option`] containing the deleted record if it exists, or [`None`] otherwise.
     pub fn delete(&self, id: u64) -> DbResult<Option<Record<T>>> {
-        let serialized_id = encode(&id)?;
-
-        // We don't need to lock table even though we write because deleting will never invalidate unique constraint.
-        if let Some(serialized) = self.root.read().unwrap().remove(serialized_id)? {
-            let record = Record {
-                id,
-                data: decode(&serialized)?,
-            };
+        // Deleting never invalidates a constraint, but the write lock keeps the change log ordered.
+        let root = self.locked();
 
+        if let Some(record) = self.tree_select(&root, id)? {
+            self.write(&root, &[(id, None)])?;
             self.dispatch_event(Event::Remove(record.clone()));
 
             Ok(Some(record))
@@ -227,8 +673,28 @@ where
         }
     }
 
+    /// Delete a record by its ID, returning only once the deletion has been persisted to disk.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The ID of the record to delete.
+    ///
+    /// # Returns
+    ///
+    /// An [`Option`] containing the deleted record if it exists, or [`None`] otherwise.
+    pub fn delete_durable(&self, id: u64) -> DbResult<Option<Record<T>>> {
+        let record = self.delete(id)?;
+        self.group_commit.sync()?;
+
+        Ok(record)
+    }
+
     /// Update one or more records by their IDs.
     ///
+    /// The updater is applied once per record, even if its ID is passed more than once. Every
+    /// record found is rewritten and dispatches an [`Event::Update`], even if the updater left its
+    /// data unchanged.
+    ///
     /// # Arguments
     ///
     /// * `ids` - The IDs of the records to update.
@@ -236,17 +702,24 @@ where
     ///
     /// # Returns
     ///
-    /// All updated records.
+    /// All updated [`Record`] instances.
     pub fn update(&self, ids: &[u64], updater: fn(T) -> T) -> DbResult<Vec<Record<T>>> {
-        let root = self.root.write().unwrap();
+        let root = self.locked();
 
+        let mut seen = HashSet::new();
         let mut records = vec![];
+        let mut old_data = vec![];
         for id in ids {
+            if !seen.insert(*id) {
+                continue;
+            }
+
             if let Some(old) = self.tree_select(&root, *id)? {
                 records.push(Record {
-                    id: old.id,
-                    data: updater(old.data),
+                    id: *id,
+                    data: updater(old.data.clone()),
                 });
+                old_data.push(old.data);
             }
         }
 
@@ -255,26 +728,208 @@ where
             self.check_constraint(&root, record, &additional)?;
         }
 
-        let mut updated = vec![];
-        for record in records {
-            root.update_and_fetch(encode(&record.id)?, |old_value| {
-                if let Some(old_value) = old_value {
-                    updated.push(record.clone());
+        let writes: Vec<(u64, Option<&T>)> = records
+            .iter()
+            .map(|record| (record.id, Some(&record.data)))
+            .collect();
+        self.write(&root, &writes)?;
 
-                    self.dispatch_event(Event::Update {
-                        id: record.id.clone(),
-                        old_data: decode(old_value).unwrap(),
-                        new_data: record.data.clone(),
-                    });
+        // Events are only dispatched once the write has succeeded, exactly once per record.
+        for (record, old_data) in records.iter().zip(old_data) {
+            self.dispatch_event(Event::Update {
+                id: record.id,
+                old_data,
+                new_data: record.data.clone(),
+            });
+        }
 
-                    Some(encode(&record.data).unwrap())
-                } else {
-                    None
+        Ok(records)
+    }
+
+    /// Update one or more records by their IDs, returning only once the update has been persisted
+    /// to disk.
+    ///
+    /// # Arguments
+    ///
+    /// * `ids` - The IDs of the records to update.
+    /// * `updater` - Closure to generate the new data based on the old data.
+    ///
+    /// # Returns
+    ///
+    /// All updated records.
+    pub fn update_durable(&self, ids: &[u64], updater: fn(T) -> T) -> DbResult<Vec<Record<T>>> {
+        let records = self.update(ids, updater)?;
+        self.group_commit.sync()?;
+
+        Ok(records)
+    }
+
+    /// Write records along with their change log entries and index changes atomically.
+    /// Any time you pass the tree it should be obtained via a write lock.
+    ///
+    /// # Arguments
+    ///
+    /// * `root` - The locked root tree.
+    /// * `writes` - The new data of each record, [`None`] removes the record.
+    fn write(&self, root: &Tree, writes: &[(u64, Option<&T>)]) -> DbResult<()> {
+        if writes.is_empty() {
+            return Ok(());
+        }
+
+        let batch = self.stage(root, writes)?;
+
+        let _commit = self.commits.read().unwrap();
+        batch::commit(&self.engine, &[&batch])?;
+        self.committed(&batch);
+
+        Ok(())
+    }
+
+    /// Encode writes along with their change log entries and the changes to every open index.
+    /// Any time you pass the tree it should be obtained via a write lock.
+    ///
+    /// # Arguments
+    ///
+    /// * `root` - The locked root tree.
+    /// * `writes` - The new data of each record, [`None`] removes the record.
+    ///
+    /// # Returns
+    ///
+    /// A [`Batch`] which can be committed along with the batches of other tables.
+    pub(crate) fn stage(&self, root: &Tree, writes: &[(u64, Option<&T>)]) -> DbResult<Batch> {
+        self.check_writable()?;
+
+        // Sequence numbers follow the last entry of the change log of the table, which can't
+        // change while the write lock is held.
+        let mut seq = self.seq()?;
+
+        let mut encoded: Vec<(Vec<u8>, Option<Vec<u8>>)> = vec![];
+        let mut log_entries = vec![];
+        for (id, data) in writes {
+            let key = encode(id)?;
+            let data = data.map(|data| self.schema.encode(data)).transpose()?;
+            encoded.push((key, data));
+
+            seq += 1;
+            log_entries.push((encode(&seq)?, encode(id)?));
+        }
+
+        // The oldest entries are dropped once the log grows past its capacity, along with the
+        // write, keeping the entries just appended so the current sequence number is known.
+        let len = self.shared.log_len.load(Ordering::SeqCst);
+        let excess = (len + log_entries.len())
+            .saturating_sub(CHANGE_LOG_CAPACITY)
+            .min(len);
+        let mut pruned = vec![];
+        for seq in self.log.iter().keys().take(excess) {
+            pruned.push(seq?.to_vec());
+        }
+        let watermark = pruned
+            .last()
+            .map(|seq| (self.pruned_key().into_bytes(), seq.clone()));
+
+        let mut indexes = vec![];
+        if !writes.is_empty() {
+            let written: Vec<(u64, Option<&[u8]>)> = writes
+                .iter()
+                .zip(&encoded)
+                .map(|((id, _), (_, data))| (*id, data.as_deref()))
+                .collect();
+
+            let mut open = self.shared.indexes.write().unwrap();
+            open.retain(|_, handles| {
+                handles.retain(|index| index.strong_count() > 0);
+                !handles.is_empty()
+            });
+
+            // Only the latest handle of each index is written, as every handle shares its trees.
+            for handles in open.values() {
+                if let Some(index) = handles.last().and_then(Weak::upgrade) {
+                    indexes.push(index.stage(&written, seq)?);
                 }
-            })?;
+            }
+        }
+
+        Ok(Batch {
+            root: root.clone(),
+            log: self.log.clone(),
+            writes: encoded,
+            log_len: len + log_entries.len() - pruned.len(),
+            log_entries,
+            pruned,
+            watermark,
+            indexes,
+        })
+    }
+
+    /// Generate the ID of a new record, failing without spending one if the table can't be
+    /// written.
+    pub(crate) fn generate_id(&self) -> DbResult<u64> {
+        self.check_writable()?;
+
+        let _commit = self.commits.read().unwrap();
+        generate_id(&self.engine, &self.meta)
+    }
+
+    /// Take the write lock of the table, shared by all its handles, see [`SharedTable::root`].
+    pub(crate) fn locked(&self) -> RwLockWriteGuard<'_, Tree> {
+        self.shared.root.write().unwrap()
+    }
+
+    /// Fail with [`TinyBaseError::ReadOnly`] if the database was opened read-only.
+    pub(crate) fn check_writable(&self) -> DbResult<()> {
+        if self.read_only {
+            return Err(TinyBaseError::ReadOnly);
+        }
+
+        Ok(())
+    }
+
+    /// Maintain an index along with every write to the table, through any of its handles.
+    /// An index with the same name replaces the previous one while it is open.
+    pub(crate) fn attach_index(&self, name: String, index: Weak<dyn IndexWriter>) {
+        let mut indexes = self.shared.indexes.write().unwrap();
+        indexes.entry(name).or_default().push(index);
+    }
+
+    /// Keep track of the change log once a batch staged by [`TableInner::stage`] is committed.
+    pub(crate) fn committed(&self, batch: &Batch) {
+        self.shared.log_len.store(batch.log_len, Ordering::SeqCst);
+    }
+
+    /// Sequence number of the latest write to the table, zero if there are none.
+    pub(crate) fn seq(&self) -> DbResult<u64> {
+        match self.log.last()? {
+            Some((seq, _)) => decode(&seq),
+            None => Ok(0),
+        }
+    }
+
+    /// Get the IDs of the records written after the given sequence number.
+    ///
+    /// # Returns
+    ///
+    /// The written IDs in the order they were written, or [`None`] if the change log no longer
+    /// reaches back that far.
+    pub(crate) fn changes_since(&self, seq: u64) -> DbResult<Option<Vec<u64>>> {
+        if let Some(pruned) = self.meta.get(self.pruned_key())? {
+            if decode::<u64>(&pruned)? > seq {
+                return Ok(None);
+            }
         }
 
-        Ok(updated)
+        let mut ids = vec![];
+        for entry in self.log.range(encode(&(seq + 1))?..) {
+            let (_, id) = entry?;
+            ids.push(decode(&id)?);
+        }
+
+        Ok(Some(ids))
+    }
+
+    /// Key under which the sequence number of the last pruned change log entry is stored.
+    fn pruned_key(&self) -> String {
+        format!("{}#pruned", self.name)
     }
 
     /// Add a constraint to the table.
@@ -285,6 +940,30 @@ where
     pub fn constraint(&self, constraint: Constraint<T>) -> DbResult<()> {
         let mut constraint_map = self.constraints.write().unwrap();
 
+        let info = match &constraint.0 {
+            ConstraintInner::Unique(index) => {
+                let tree = index.idx_name();
+                let prefix = catalog::index_tree(&self.name, "");
+                ConstraintInfo::Unique {
+                    index: tree.strip_prefix(&prefix).unwrap_or(&tree).to_owned(),
+                }
+            }
+            ConstraintInner::Check(_) => ConstraintInfo::Check {
+                position: constraint_map
+                    .iter()
+                    .filter(|other| matches!(other.0, ConstraintInner::Check(_)))
+                    .count(),
+            },
+        };
+        if !self.read_only {
+            let _commit = self.commits.read().unwrap();
+            catalog::update(&self.engine, &self.name, |table| {
+                if !table.constraints.contains(&info) {
+                    table.constraints.push(info.clone());
+                }
+            })?;
+        }
+
         match &constraint.0 {
             // Check if index has already been added if constraint is unique.
             ConstraintInner::Unique(index) => {
@@ -311,13 +990,63 @@ where
     }
 
     /// Dispatch event to all receivers.
-    fn dispatch_event(&self, event: Event<T>) {
-        for sender in self.senders.read().unwrap().values() {
-            sender.send(event.clone()).unwrap();
+    ///
+    /// Writers never wait for subscribers, so those which are dropped or have no room left for
+    /// the event are removed.
+    pub(crate) fn dispatch_event(&self, event: Event<T>) {
+        let disconnected: Vec<u64> = self
+            .senders
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|(_, sender)| !sender.try_send(event.clone()))
+            .map(|(id, _)| *id)
+            .collect();
+
+        if !disconnected.is_empty() {
+            let mut senders = self.senders.write().unwrap();
+            for id in disconnected {
+                senders.remove(&id);
+            }
         }
     }
 }
 
+/// Lazy iterator over the records of a table.
+pub struct TableIter<T> {
+    inner: sled::Iter,
+    schema: Arc<Schema<T>>,
+}
+
+impl<T: TableType> Iterator for TableIter<T> {
+    type Item = DbResult<Record<T>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let entry = self.inner.next()?;
+        Some(decode_entry(&self.schema, entry))
+    }
+}
+
+impl<T: TableType> DoubleEndedIterator for TableIter<T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let entry = self.inner.next_back()?;
+        Some(decode_entry(&self.schema, entry))
+    }
+}
+
+/// Decode a raw entry of the root tree into a record.
+fn decode_entry<T: TableType>(
+    schema: &Schema<T>,
+    entry: sled::Result<(IVec, IVec)>,
+) -> DbResult<Record<T>> {
+    let (key, value) = entry?;
+
+    Ok(Record {
+        id: decode(&key)?,
+        data: schema.decode(&value)?,
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -354,6 +1083,125 @@ mod tests {
         assert!(table.select(id).unwrap().is_none());
     }
 
+    #[test]
+    fn table_iter() {
+        let db = TinyBase::new(None, true);
+        let table: Table<String> = db.open_table("test_table").unwrap();
+
+        assert!(table.is_empty());
+
+        // Insert string values into the table
+        let id1 = table.insert("value1".to_string()).unwrap();
+        let id2 = table.insert("value2".to_string()).unwrap();
+        let id3 = table.insert("value3".to_string()).unwrap();
+
+        assert_eq!(table.len(), 3);
+        assert!(!table.is_empty());
+        assert_eq!(table.ids().unwrap(), vec![id1, id2, id3]);
+
+        let records: Vec<Record<String>> = table.iter().collect::<DbResult<_>>().unwrap();
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[0].data, "value1");
+        assert_eq!(records[2].data, "value3");
+
+        // Iterators are lazy and can be consumed from either end.
+        let last = table.iter().next_back().unwrap().unwrap();
+        assert_eq!(last.id, id3);
+
+        table.delete(id2).unwrap();
+        let scanned = table.scan().unwrap();
+        assert_eq!(scanned.len(), 2);
+        assert_eq!(scanned[1].id, id3);
+    }
+
+    #[test]
+    fn table_change_log() {
+        let db = TinyBase::new(None, true);
+        let table: Table<String> = db.open_table("test_table").unwrap();
+        let other: Table<String> = db.open_table("other").unwrap();
+
+        let id = table.insert("value1".to_string()).unwrap();
+        other.insert("value2".to_string()).unwrap();
+        table.delete(id).unwrap();
+
+        // Each table counts its own writes, so IDs aren't spent on the change log.
+        assert_eq!(table.seq().unwrap(), 2);
+        assert_eq!(other.seq().unwrap(), 1);
+        assert_eq!(table.changes_since(0).unwrap(), Some(vec![id, id]));
+
+        // The oldest entries are dropped along with the write growing the log past its capacity.
+        db.transaction(|tx| {
+            for _ in 0..CHANGE_LOG_CAPACITY {
+                tx.insert(&table, "value".to_string())?;
+            }
+            Ok(())
+        })
+        .unwrap();
+        table.insert("value3".to_string()).unwrap();
+        assert_eq!(table.log.len(), CHANGE_LOG_CAPACITY);
+        assert_eq!(table.changes_since(0).unwrap(), None);
+        assert_eq!(
+            table.changes_since(table.seq().unwrap() - 1).unwrap(),
+            Some(vec![table.scan().unwrap().last().unwrap().id])
+        );
+    }
+
+    #[test]
+    fn table_shared_indexes() {
+        let db = TinyBase::new(None, true);
+        let table: Table<String> = db.open_table("test_table").unwrap();
+        let other: Table<String> = db.open_table("test_table").unwrap();
+
+        let name = table
+            .create_index("name", |value| value.to_owned())
+            .unwrap();
+
+        // Indexes are written along with the writes made through every handle of the table.
+        let id = other.insert("value1".to_string()).unwrap();
+        assert_eq!(name.select(&"value1".to_string()).unwrap()[0].id, id);
+
+        other.delete(id).unwrap();
+        assert!(name.select(&"value1".to_string()).unwrap().is_empty());
+        assert_eq!(table.seq().unwrap(), 2);
+    }
+
+    #[test]
+    fn table_update_events() {
+        let db = TinyBase::new(None, true);
+        let table: Table<String> = db.open_table("test_table").unwrap();
+
+        let id1 = table.insert("value1".to_string()).unwrap();
+        let id2 = table.insert("value2".to_string()).unwrap();
+
+        let subscriber = table.subscribe().unwrap();
+
+        // Repeated IDs are updated once, records left unchanged are still written.
+        let updated = table
+            .update(&[id1, id1, id2], |value| {
+                if value == "value1" {
+                    format!("{}!", value)
+                } else {
+                    value
+                }
+            })
+            .unwrap();
+
+        assert_eq!(updated.len(), 2);
+        assert_eq!(table.select(id1).unwrap().unwrap().data, "value1!");
+
+        assert!(matches!(
+            subscriber.try_recv().unwrap(),
+            Some(Event::Update { id, old_data, new_data })
+                if id == id1 && old_data == "value1" && new_data == "value1!"
+        ));
+        assert!(matches!(
+            subscriber.try_recv().unwrap(),
+            Some(Event::Update { id, old_data, new_data })
+                if id == id2 && old_data == "value2" && new_data == "value2"
+        ));
+        assert!(subscriber.try_recv().unwrap().is_none());
+    }
+
     #[test]
     fn table_update() {
         let db = TinyBase::new(None, true);
@@ -375,4 +1223,35 @@ mod tests {
         assert_eq!(updated_records[1].id, id2);
         assert_eq!(updated_records[1].data, "updated_value");
     }
+
+    #[test]
+    fn table_durable_writes() {
+        let db = TinyBase::builder()
+            .temporary(true)
+            .flush_every_ms(None)
+            .group_commit(std::time::Duration::from_millis(5))
+            .open()
+            .unwrap();
+        let table: Table<String> = db.open_table("test_table").unwrap();
+
+        let writers: Vec<_> = (0..4)
+            .map(|i| {
+                let table = table.clone();
+                std::thread::spawn(move || table.insert_durable(format!("value{}", i)))
+            })
+            .collect();
+        let ids: Vec<u64> = writers
+            .into_iter()
+            .map(|writer| writer.join().unwrap().unwrap())
+            .collect();
+
+        table
+            .update_durable(&ids[..1], |_| "updated".to_string())
+            .unwrap();
+        table.delete_durable(ids[1]).unwrap();
+        db.flush().unwrap();
+
+        assert_eq!(table.select(ids[0]).unwrap().unwrap().data, "updated");
+        assert_eq!(table.scan().unwrap().len(), 3);
+    }
 }
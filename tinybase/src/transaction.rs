@@ -0,0 +1,337 @@
+use std::any::Any;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, RwLock, RwLockWriteGuard};
+
+use sled::{Db, Tree};
+
+use crate::batch::{self, Batch};
+use crate::record::Record;
+use crate::result::DbResult;
+use crate::subscriber::Event;
+use crate::table::{Table, TableType};
+
+/// Writes of a single table which are ready to be committed.
+struct Prepared {
+    batch: Batch,
+    /// Keeps track of the change log and dispatches the events of the writes, called once they
+    /// have been committed.
+    dispatch: Box<dyn FnOnce(&Batch)>,
+}
+
+/// Type erased pending writes of a single table.
+trait PendingWrites {
+    /// Name of the table, used to lock tables in a consistent order.
+    fn name(&self) -> &str;
+    /// Obtain the write lock of the table.
+    fn lock(&self) -> RwLockWriteGuard<'_, Tree>;
+    /// Check constraints and encode the writes against the locked tree.
+    fn prepare(&self, root: &Tree) -> DbResult<Prepared>;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+/// Pending writes of a single table.
+struct PendingTable<T: TableType + 'static> {
+    table: Table<T>,
+    /// Final state of each written record, [`None`] if the record is removed.
+    writes: BTreeMap<u64, Option<T>>,
+}
+
+impl<T: TableType + 'static> PendingWrites for PendingTable<T> {
+    fn name(&self) -> &str {
+        &self.table.name
+    }
+
+    fn lock(&self) -> RwLockWriteGuard<'_, Tree> {
+        self.table.locked()
+    }
+
+    fn prepare(&self, root: &Tree) -> DbResult<Prepared> {
+        self.table.check_batch(root, &self.writes)?;
+
+        let mut writes = vec![];
+        let mut events = vec![];
+
+        for (id, data) in &self.writes {
+            let old = self.table.tree_select(root, *id)?;
+
+            match (old, data) {
+                (None, Some(data)) => events.push(Event::Insert(Record {
+                    id: *id,
+                    data: data.clone(),
+                })),
+                (Some(old), Some(data)) => events.push(Event::Update {
+                    id: *id,
+                    old_data: old.data,
+                    new_data: data.clone(),
+                }),
+                (Some(old), None) => events.push(Event::Remove(old)),
+                // Inserted and removed within the transaction.
+                (None, None) => continue,
+            }
+
+            writes.push((*id, data.as_ref()));
+        }
+
+        let batch = self.table.stage(root, &writes)?;
+        let table = self.table.clone();
+
+        Ok(Prepared {
+            batch,
+            dispatch: Box::new(move |batch| {
+                table.committed(batch);
+
+                for event in events {
+                    table.dispatch_event(event);
+                }
+            }),
+        })
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+/// A set of writes across one or more tables which are committed atomically.
+///
+/// Writes are buffered until the transaction is committed. Constraints are checked and events are
+/// dispatched to indexes only on commit, so an aborted transaction leaves no trace.
+pub struct Transaction {
+    engine: Db,
+    /// Held while committing, so a backup never observes the transaction partially.
+    commits: Arc<RwLock<()>>,
+    /// Pending writes keyed by the address of the table.
+    tables: HashMap<usize, Box<dyn PendingWrites>>,
+}
+
+impl Transaction {
+    pub(crate) fn new(engine: &Db, commits: Arc<RwLock<()>>) -> Self {
+        Self {
+            engine: engine.clone(),
+            commits,
+            tables: HashMap::new(),
+        }
+    }
+
+    /// Get the pending writes for a table.
+    fn pending<T: TableType + 'static>(&mut self, table: &Table<T>) -> &mut PendingTable<T> {
+        self.tables
+            .entry(Arc::as_ptr(&table.0) as usize)
+            .or_insert_with(|| {
+                Box::new(PendingTable {
+                    table: table.clone(),
+                    writes: BTreeMap::new(),
+                })
+            })
+            .as_any_mut()
+            .downcast_mut()
+            .unwrap()
+    }
+
+    /// Insert a new record into a table.
+    ///
+    /// # Arguments
+    ///
+    /// * `table` - The table to insert into.
+    /// * `value` - The value to insert.
+    ///
+    /// # Returns
+    ///
+    /// The ID the new record will have once committed.
+    pub fn insert<T: TableType + 'static>(&mut self, table: &Table<T>, value: T) -> DbResult<u64> {
+        let id = table.generate_id()?;
+        self.pending(table).writes.insert(id, Some(value));
+
+        Ok(id)
+    }
+
+    /// Select a record by its ID, including writes made earlier in this transaction.
+    ///
+    /// # Arguments
+    ///
+    /// * `table` - The table to select from.
+    /// * `id` - The ID of the record to select.
+    ///
+    /// # Returns
+    ///
+    /// An [`Option`] containing the selected record if it exists, or [`None`] otherwise.
+    pub fn select<T: TableType + 'static>(
+        &mut self,
+        table: &Table<T>,
+        id: u64,
+    ) -> DbResult<Option<Record<T>>> {
+        match self.pending(table).writes.get(&id) {
+            Some(data) => Ok(data.clone().map(|data| Record { id, data })),
+            None => table.select(id),
+        }
+    }
+
+    /// Update one or more records by their IDs.
+    ///
+    /// # Arguments
+    ///
+    /// * `table` - The table containing the records.
+    /// * `ids` - The IDs of the records to update.
+    /// * `updater` - Closure to generate the new data based on the old data.
+    ///
+    /// # Returns
+    ///
+    /// All updated records.
+    pub fn update<T: TableType + 'static>(
+        &mut self,
+        table: &Table<T>,
+        ids: &[u64],
+        updater: fn(T) -> T,
+    ) -> DbResult<Vec<Record<T>>> {
+        let mut updated = vec![];
+        for id in ids {
+            if let Some(old) = self.select(table, *id)? {
+                let data = updater(old.data);
+                self.pending(table).writes.insert(*id, Some(data.clone()));
+                updated.push(Record { id: *id, data });
+            }
+        }
+
+        Ok(updated)
+    }
+
+    /// Delete a record by its ID.
+    ///
+    /// # Arguments
+    ///
+    /// * `table` - The table containing the record.
+    /// * `id` - The ID of the record to delete.
+    ///
+    /// # Returns
+    ///
+    /// An [`Option`] containing the deleted record if it exists, or [`None`] otherwise.
+    pub fn delete<T: TableType + 'static>(
+        &mut self,
+        table: &Table<T>,
+        id: u64,
+    ) -> DbResult<Option<Record<T>>> {
+        let record = self.select(table, id)?;
+        if record.is_some() {
+            self.pending(table).writes.insert(id, None);
+        }
+
+        Ok(record)
+    }
+
+    /// Commit all pending writes atomically.
+    pub(crate) fn commit(self) -> DbResult<()> {
+        if self.tables.is_empty() {
+            return Ok(());
+        }
+
+        let mut tables: Vec<&dyn PendingWrites> = self.tables.values().map(AsRef::as_ref).collect();
+        // Lock in a consistent order so concurrent transactions can't deadlock.
+        tables.sort_by(|left, right| left.name().cmp(right.name()));
+
+        let roots: Vec<RwLockWriteGuard<'_, Tree>> =
+            tables.iter().map(|table| table.lock()).collect();
+
+        let mut prepared = vec![];
+        for (table, root) in tables.iter().zip(&roots) {
+            prepared.push(table.prepare(root)?);
+        }
+
+        let batches: Vec<&Batch> = prepared.iter().map(|prepared| &prepared.batch).collect();
+        let commit = self.commits.read().unwrap();
+        batch::commit(&self.engine, &batches)?;
+
+        // Dispatch while still holding the locks so events stay ordered with other writers.
+        for prepared in prepared {
+            (prepared.dispatch)(&prepared.batch);
+        }
+
+        drop(commit);
+        drop(roots);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::result::TinyBaseError;
+    use crate::{Constraint, TinyBase};
+
+    #[test]
+    fn transaction_commit() {
+        let db = TinyBase::new(None, true);
+        let accounts: Table<String> = db.open_table("accounts").unwrap();
+        let ledger: Table<u64> = db.open_table("ledger").unwrap();
+
+        let name = accounts
+            .create_index("name", |value| value.to_owned())
+            .unwrap();
+
+        let (account, entry) = db
+            .transaction(|tx| {
+                let account = tx.insert(&accounts, "alice".to_string())?;
+                let entry = tx.insert(&ledger, 100)?;
+
+                // Writes are visible within the transaction, but not outside of it.
+                assert!(tx.select(&accounts, account)?.is_some());
+                assert!(accounts.select(account)?.is_none());
+
+                tx.update(&ledger, &[entry], |amount| amount * 2)?;
+
+                Ok((account, entry))
+            })
+            .unwrap();
+
+        assert_eq!(accounts.select(account).unwrap().unwrap().data, "alice");
+        assert_eq!(ledger.select(entry).unwrap().unwrap().data, 200);
+
+        // Indexes receive the committed writes.
+        assert_eq!(name.select(&"alice".to_string()).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn transaction_rollback() {
+        let db = TinyBase::new(None, true);
+        let accounts: Table<String> = db.open_table("accounts").unwrap();
+        let ledger: Table<u64> = db.open_table("ledger").unwrap();
+
+        let name = accounts
+            .create_index("name", |value| value.to_owned())
+            .unwrap();
+        accounts.constraint(Constraint::unique(&name)).unwrap();
+
+        let bob = accounts.insert("bob".to_string()).unwrap();
+
+        // An error returned from the closure aborts every write.
+        let result: DbResult<()> = db.transaction(|tx| {
+            tx.insert(&ledger, 100)?;
+            tx.delete(&accounts, bob)?;
+            Err(TinyBaseError::Condition)
+        });
+
+        assert!(result.is_err());
+        assert!(ledger.is_empty());
+        assert!(accounts.select(bob).unwrap().is_some());
+
+        // A constraint violation in one table aborts writes to every table.
+        let result = db.transaction(|tx| {
+            tx.insert(&ledger, 100)?;
+            tx.insert(&accounts, "bob".to_string())
+        });
+
+        assert!(matches!(result, Err(TinyBaseError::Exists { .. })));
+        assert!(ledger.is_empty());
+        assert_eq!(accounts.len(), 1);
+
+        // Replacing a unique value within the same transaction is allowed.
+        db.transaction(|tx| {
+            tx.delete(&accounts, bob)?;
+            tx.insert(&accounts, "bob".to_string())
+        })
+        .unwrap();
+
+        assert_eq!(accounts.len(), 1);
+        assert!(accounts.select(bob).unwrap().is_none());
+    }
+}